@@ -9,8 +9,8 @@ import threading
 import numpy as np
 import soundfile as sf
 import nemo.collections.asr as nemo_asr
-from fastapi import FastAPI, File, UploadFile, WebSocket, WebSocketDisconnect
-from fastapi.responses import PlainTextResponse
+from fastapi import FastAPI, File, Form, UploadFile, WebSocket, WebSocketDisconnect
+from fastapi.responses import JSONResponse, PlainTextResponse
 
 # Global model reference
 asr_model = None
@@ -64,8 +64,13 @@ def _transcribe_samples(samples: np.ndarray) -> str:
 
 
 @app.post("/transcribe/", response_class=PlainTextResponse)
-async def transcribe(file: UploadFile = File(...)) -> str:
-    """Transcribe an uploaded audio file and return the text."""
+async def transcribe(file: UploadFile = File(...), timestamps: bool = Form(False)):
+    """Transcribe an uploaded audio file and return the text.
+
+    With the form field timestamps=true, returns JSON instead:
+      {"text": "...", "segments": [{"start": 0.0, "end": 1.2, "text": "..."}]}
+    with start/end in seconds (the same shape as OpenAI's verbose_json).
+    """
     suffix = os.path.splitext(file.filename)[1] if file.filename else ".wav"
     with tempfile.NamedTemporaryFile(suffix=suffix, delete=False) as tmp:
         content = await file.read()
@@ -73,6 +78,20 @@ async def transcribe(file: UploadFile = File(...)) -> str:
         tmp_path = tmp.name
 
     try:
+        if timestamps:
+            result = asr_model.transcribe([tmp_path], timestamps=True)
+            hyp = result[0] if result else None
+            segments = []
+            if hyp is not None and getattr(hyp, "timestamp", None):
+                for seg in hyp.timestamp.get("segment", []):
+                    segments.append({
+                        "start": seg["start"],
+                        "end": seg["end"],
+                        "text": seg["segment"].strip(),
+                    })
+            text = hyp.text.strip() if hyp is not None else ""
+            return JSONResponse({"text": text, "segments": segments})
+
         result = asr_model.transcribe([tmp_path])
         if result:
             hyp = result[0]
@@ -102,7 +121,10 @@ async def stream_transcribe(websocket: WebSocket):
 
     Protocol:
       Client sends:
-        - Binary frames: raw s16le PCM audio at 16kHz mono
+        - Optional text frame first: {"type": "start", "format": "s16le" | "f32le",
+          "sample_rate": 16000, "prompt": "..."}. The prompt is accepted but
+          ignored; NeMo models don't take one.
+        - Binary frames: raw PCM audio at 16kHz mono (s16le unless declared)
         - Text frame: {"type": "done"} to signal end of audio
 
       Server sends:
@@ -114,6 +136,7 @@ async def stream_transcribe(websocket: WebSocket):
     audio_lock = asyncio.Lock()
     audio_chunks: list[np.ndarray] = []
     running = True
+    audio_format = "s16le"
 
     async def transcription_worker():
         """Periodically transcribe accumulated audio and push partial results."""
@@ -171,11 +194,14 @@ async def stream_transcribe(websocket: WebSocket):
                 break
 
             if "bytes" in message and message["bytes"]:
-                # Raw s16le PCM audio chunk
+                # Raw PCM audio chunk
                 raw = message["bytes"]
-                chunk = (
-                    np.frombuffer(raw, dtype=np.int16).astype(np.float32) / 32768.0
-                )
+                if audio_format == "f32le":
+                    chunk = np.frombuffer(raw, dtype="<f4").astype(np.float32)
+                else:
+                    chunk = (
+                        np.frombuffer(raw, dtype=np.int16).astype(np.float32) / 32768.0
+                    )
                 async with audio_lock:
                     audio_chunks.append(chunk)
 
@@ -184,7 +210,9 @@ async def stream_transcribe(websocket: WebSocket):
                     data = json.loads(message["text"])
                 except (json.JSONDecodeError, TypeError):
                     continue
-                if data.get("type") == "done":
+                if data.get("type") == "start":
+                    audio_format = data.get("format", "s16le")
+                elif data.get("type") == "done":
                     break
 
     except WebSocketDisconnect:
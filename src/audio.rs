@@ -1,11 +1,18 @@
+use crate::config::AudioFormat;
 use anyhow::{Context, Result};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{SampleRate, StreamConfig};
+use std::io::Write;
+use std::process::{Command, Stdio};
 use std::sync::{Arc, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
 use tracing::{info, warn};
 
 const WHISPER_SAMPLE_RATE: u32 = 16_000;
+/// Frame length for pause detection.
+const PAUSE_FRAME_SECS: f32 = 0.03;
+/// Frames quieter than this (about -40 dBFS) count as silence.
+const SILENCE_RMS: f32 = 0.01;
 
 /// Lightweight, Send+Sync handle to the audio buffer.
 /// Can be cloned and sent to other threads for snapshotting.
@@ -91,6 +98,13 @@ impl AudioCapture {
         info!("recording started");
     }
 
+    /// Pause or resume accumulating samples, keeping what's buffered. The
+    /// stream keeps running, so resuming is instant.
+    pub fn set_paused(&self, paused: bool) {
+        self.recording.store(!paused, Ordering::Relaxed);
+        info!(paused, "recording pause toggled");
+    }
+
     /// Stop accumulating and return the buffered samples.
     pub fn stop_recording(&self) -> Vec<f32> {
         self.recording.store(false, Ordering::Relaxed);
@@ -100,20 +114,189 @@ impl AudioCapture {
         samples
     }
 
-    /// Write f32 samples to a 16kHz mono WAV file.
-    pub fn write_wav(samples: &[f32], path: &std::path::Path) -> Result<()> {
+    /// Write f32 samples to a 16kHz mono WAV file, as 16-bit integer or
+    /// 32-bit float samples depending on `format`.
+    pub fn write_wav(samples: &[f32], path: &std::path::Path, format: AudioFormat) -> Result<()> {
+        let (bits_per_sample, sample_format) = match format {
+            AudioFormat::S16le => (16, hound::SampleFormat::Int),
+            AudioFormat::F32le => (32, hound::SampleFormat::Float),
+        };
         let spec = hound::WavSpec {
             channels: 1,
             sample_rate: WHISPER_SAMPLE_RATE,
-            bits_per_sample: 16,
-            sample_format: hound::SampleFormat::Int,
+            bits_per_sample,
+            sample_format,
         };
         let mut writer = hound::WavWriter::create(path, spec)?;
-        for &sample in samples {
-            let s = (sample * 32767.0).clamp(-32768.0, 32767.0) as i16;
-            writer.write_sample(s)?;
+        match format {
+            AudioFormat::S16le => {
+                for &sample in samples {
+                    let s = (sample * 32767.0).clamp(-32768.0, 32767.0) as i16;
+                    writer.write_sample(s)?;
+                }
+            }
+            AudioFormat::F32le => {
+                for &sample in samples {
+                    writer.write_sample(sample)?;
+                }
+            }
         }
         writer.finalize()?;
         Ok(())
     }
 }
+
+/// Find pauses of at least `min_secs` between stretches of speech, returned
+/// as the position of each pause's midpoint as a fraction of the recording.
+/// Silence before the first and after the last speech is ignored. A pause is
+/// always at least one silent frame, however small `min_secs` is.
+pub fn find_pauses(samples: &[f32], min_secs: f32) -> Vec<f32> {
+    let frame_len = (WHISPER_SAMPLE_RATE as f32 * PAUSE_FRAME_SECS) as usize;
+    let min_frames = ((min_secs / PAUSE_FRAME_SECS).ceil() as usize).max(1);
+    let frames = samples.len() / frame_len;
+    if frames == 0 {
+        return Vec::new();
+    }
+
+    let mut pauses = Vec::new();
+    let mut silent_run = 0;
+    let mut heard_speech = false;
+    for (i, frame) in samples.chunks_exact(frame_len).enumerate() {
+        let rms = (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt();
+        if rms < SILENCE_RMS {
+            silent_run += 1;
+            continue;
+        }
+        if heard_speech && silent_run >= min_frames {
+            let mid = i as f32 - silent_run as f32 / 2.0;
+            pauses.push(mid / frames as f32);
+        }
+        heard_speech = true;
+        silent_run = 0;
+    }
+    pauses
+}
+
+/// Decode a complete audio file to 16kHz mono samples. WAV is read directly
+/// (downmixed and resampled as needed); anything else is converted by ffmpeg.
+pub fn decode_audio(bytes: &[u8]) -> Result<Vec<f32>> {
+    match hound::WavReader::new(bytes) {
+        Ok(reader) => read_wav(reader),
+        Err(_) => decode_with_ffmpeg(bytes),
+    }
+}
+
+fn read_wav(reader: hound::WavReader<&[u8]>) -> Result<Vec<f32>> {
+    let spec = reader.spec();
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .into_samples::<f32>()
+            .collect::<Result<_, _>>()
+            .context("invalid WAV data")?,
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1u32 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .into_samples::<i32>()
+                .map(|s| s.map(|s| s as f32 * scale))
+                .collect::<Result<_, _>>()
+                .context("invalid WAV data")?
+        }
+    };
+
+    let channels = spec.channels.max(1) as usize;
+    let mono: Vec<f32> = samples
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect();
+    info!(
+        sample_rate = spec.sample_rate,
+        channels,
+        duration_secs = mono.len() as f32 / spec.sample_rate as f32,
+        "decoded WAV"
+    );
+    Ok(resample(&mono, spec.sample_rate, WHISPER_SAMPLE_RATE))
+}
+
+/// Convert any format ffmpeg understands to raw 16kHz mono f32.
+fn decode_with_ffmpeg(bytes: &[u8]) -> Result<Vec<f32>> {
+    let mut child = Command::new("ffmpeg")
+        .args(["-loglevel", "error", "-i", "pipe:0", "-ac", "1", "-ar"])
+        .arg(WHISPER_SAMPLE_RATE.to_string())
+        .args(["-f", "f32le", "pipe:1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("input is not a WAV file and ffmpeg is not available to decode it")?;
+
+    let mut stdin = child.stdin.take().context("ffmpeg stdin unavailable")?;
+    let output = std::thread::scope(|scope| {
+        // Feed stdin from another thread so a full stdout pipe can't deadlock.
+        // A write error just means ffmpeg gave up early; its exit status says why.
+        scope.spawn(move || {
+            let _ = stdin.write_all(bytes);
+        });
+        child.wait_with_output()
+    })
+    .context("failed to run ffmpeg")?;
+
+    if !output.status.success() {
+        anyhow::bail!("ffmpeg could not decode the input ({})", output.status);
+    }
+    Ok(output
+        .stdout
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+/// Linear-interpolation resample. Plenty for speech headed to an ASR model.
+fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let step = from_rate as f64 / to_rate as f64;
+    let len = (samples.len() as f64 / step) as usize;
+    (0..len)
+        .map(|i| {
+            let pos = i as f64 * step;
+            let idx = pos as usize;
+            let frac = (pos - idx as f64) as f32;
+            let a = samples[idx];
+            let b = samples.get(idx + 1).copied().unwrap_or(a);
+            a + (b - a) * frac
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `speech`/`silence` alternating runs, in frames.
+    fn frames(runs: &[(f32, usize)]) -> Vec<f32> {
+        let frame_len = (WHISPER_SAMPLE_RATE as f32 * PAUSE_FRAME_SECS) as usize;
+        runs.iter()
+            .flat_map(|&(level, count)| std::iter::repeat_n(level, count * frame_len))
+            .collect()
+    }
+
+    #[test]
+    fn find_pauses_midpoint() {
+        let samples = frames(&[(0.5, 10), (0.0, 10), (0.5, 10)]);
+        assert_eq!(find_pauses(&samples, 0.25), vec![0.5]);
+        assert!(find_pauses(&samples, 1.5).is_empty());
+    }
+
+    #[test]
+    fn find_pauses_zero_threshold_needs_silence() {
+        let samples = frames(&[(0.5, 10), (0.0, 10), (0.5, 10)]);
+        assert_eq!(find_pauses(&samples, 0.0), vec![0.5]);
+        assert!(find_pauses(&frames(&[(0.5, 30)]), 0.0).is_empty());
+    }
+
+    #[test]
+    fn find_pauses_ignores_leading_and_trailing_silence() {
+        let samples = frames(&[(0.0, 20), (0.5, 10), (0.0, 20)]);
+        assert!(find_pauses(&samples, 0.25).is_empty());
+    }
+}
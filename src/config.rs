@@ -1,25 +1,577 @@
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
 use serde::Deserialize;
+use std::num::NonZeroU64;
 use std::path::PathBuf;
-use tracing::{debug, warn};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info, warn};
+
+/// Commented template written by `--init-config`. Keep in sync with the structs below.
+const DEFAULT_CONFIG_TOML: &str = include_str!("default_config.toml");
+
+/// How often the config file is checked for changes.
+const RELOAD_POLL_INTERVAL: Duration = Duration::from_secs(2);
 
 const DEFAULT_SERVER: &str = "http://localhost:5051";
+const DEFAULT_RETRIES: u32 = 3;
+const DEFAULT_RETRY_BASE_MS: u64 = 200;
+const DEFAULT_OPENAI_MODEL: &str = "whisper-1";
+const DEFAULT_STREAM_PATH: &str = "/ws/stream";
+const DEFAULT_STREAM_INTERVAL_MS: NonZeroU64 = NonZeroU64::new(100).unwrap();
+const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 3_000;
+const DEFAULT_HEALTH_INTERVAL_MS: u64 = 5_000;
 
 #[derive(Debug, Deserialize, Default)]
 pub struct Config {
     #[serde(default)]
     pub server: ServerConfig,
+    #[serde(default)]
+    pub text: TextConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
+    pub commands: CommandsConfig,
+    #[serde(default)]
+    pub input: InputConfig,
+    #[serde(default)]
+    pub midi: MidiConfig,
+    #[serde(default)]
+    pub feedback: FeedbackConfig,
+    #[serde(default)]
+    pub output: OutputConfig,
+    #[serde(default)]
+    pub overlay: OverlayConfig,
+    #[serde(default)]
+    pub control: ControlConfig,
+    /// Per-application paste settings, matched on the focused window's class.
+    #[serde(default)]
+    pub app_overrides: Vec<AppOverride>,
+    /// Pattern -> replacement, applied to the final transcription before pasting,
+    /// in the order they appear in the file.
+    #[serde(default)]
+    pub replacements: IndexMap<String, Replacement>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TextConfig {
+    /// Capitalize sentence starts of the final transcription.
+    #[serde(default)]
+    pub autoformat: bool,
+    /// With `autoformat`, end the text with a period if it has no terminal punctuation.
+    #[serde(default = "default_true")]
+    pub add_period: bool,
+    /// Turn long pauses between speech into paragraph breaks.
+    #[serde(default)]
+    pub paragraph_on_pause: bool,
+    /// Minimum silence, in seconds, that counts as a paragraph pause.
+    #[serde(default = "default_pause_secs")]
+    pub pause_secs: f32,
+}
+
+impl Default for TextConfig {
+    fn default() -> Self {
+        Self {
+            autoformat: false,
+            add_period: true,
+            paragraph_on_pause: false,
+            pause_secs: default_pause_secs(),
+        }
+    }
+}
+
+fn default_pause_secs() -> f32 {
+    1.5
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HistoryConfig {
+    /// Append each transcription to `$XDG_STATE_HOME/justspeak/history.jsonl`.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Spoken editing commands, recognized in the final transcription.
+#[derive(Debug, Deserialize)]
+pub struct CommandsConfig {
+    /// Off by default so dictating the command words literally still works.
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_new_line")]
+    pub new_line: String,
+    #[serde(default = "default_new_paragraph")]
+    pub new_paragraph: String,
+    /// Discards everything dictated before it in the same utterance.
+    #[serde(default = "default_scratch_that")]
+    pub scratch_that: String,
+}
+
+impl Default for CommandsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            new_line: default_new_line(),
+            new_paragraph: default_new_paragraph(),
+            scratch_that: default_scratch_that(),
+        }
+    }
+}
+
+fn default_new_line() -> String {
+    "new line".to_string()
+}
+
+fn default_new_paragraph() -> String {
+    "new paragraph".to_string()
+}
+
+fn default_scratch_that() -> String {
+    "scratch that".to_string()
+}
+
+/// Keyboard and recording settings.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct InputConfig {
+    /// evdev key name (e.g. `KEY_F9`) that removes the last pasted text.
+    /// Read once at startup.
+    #[serde(default)]
+    pub undo_key: Option<String>,
+    /// Key that discards the current recording without pasting. Read once at startup.
+    #[serde(default = "default_abort_key")]
+    pub abort_key: Option<String>,
+    /// Key that pauses and resumes the current recording. Read once at startup.
+    #[serde(default)]
+    pub pause_key: Option<String>,
+    /// Recordings shorter than this are dropped. 0 disables the check.
+    #[serde(default = "default_min_duration_secs")]
+    pub min_duration_secs: f32,
+    /// A trigger press or release reversed within this many milliseconds is
+    /// contact bounce and ignored. 0 disables debouncing.
+    #[serde(default = "default_debounce_ms")]
+    pub debounce_ms: u64,
+    /// Grab the keyboards so AltGr doesn't also reach other apps; everything
+    /// else is re-emitted through a uinput device. Read once at startup.
+    #[serde(default)]
+    pub grab: bool,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            undo_key: None,
+            abort_key: default_abort_key(),
+            pause_key: None,
+            min_duration_secs: default_min_duration_secs(),
+            debounce_ms: default_debounce_ms(),
+            grab: false,
+        }
+    }
+}
+
+fn default_abort_key() -> Option<String> {
+    Some("KEY_ESC".to_string())
+}
+
+fn default_min_duration_secs() -> f32 {
+    0.3
+}
+
+fn default_debounce_ms() -> u64 {
+    40
+}
+
+/// MIDI foot pedal settings. Read once at startup.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct MidiConfig {
+    /// Substring of the MIDI input port name to connect to.
+    #[serde(default = "default_midi_port_match")]
+    pub port_match: String,
+    /// Which of the ports matching `port_match` to use (0 = first).
+    #[serde(default)]
+    pub port_index: usize,
+    /// Exact port name; takes precedence over `port_match`/`port_index`.
+    #[serde(default)]
+    pub port_name: Option<String>,
+    /// Which message type triggers recording.
+    #[serde(default)]
+    pub trigger: MidiTrigger,
+    /// Note number for `trigger = "note"`.
+    #[serde(default = "default_midi_note")]
+    pub note: u8,
+    /// Control Change number the pedal sends.
+    #[serde(default = "default_midi_controller")]
+    pub controller: u8,
+    /// CC value meaning "pressed".
+    #[serde(default = "default_midi_press_value")]
+    pub press_value: u8,
+    /// CC value meaning "released".
+    #[serde(default)]
+    pub release_value: u8,
+    /// Second pedal CC that removes the last pasted text when pressed.
+    #[serde(default)]
+    pub undo_controller: Option<u8>,
+    /// Pedal CC that pauses and resumes the current recording when pressed.
+    #[serde(default)]
+    pub pause_controller: Option<u8>,
+}
+
+impl Default for MidiConfig {
+    fn default() -> Self {
+        Self {
+            port_match: default_midi_port_match(),
+            port_index: 0,
+            port_name: None,
+            trigger: MidiTrigger::default(),
+            note: default_midi_note(),
+            controller: default_midi_controller(),
+            press_value: default_midi_press_value(),
+            release_value: 0,
+            undo_controller: None,
+            pause_controller: None,
+        }
+    }
+}
+
+/// MIDI message type the pedal sends.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MidiTrigger {
+    /// Control Change on `controller`, with `press_value`/`release_value`.
+    #[default]
+    Cc,
+    /// Note On/Off on `note` (Note On with velocity 0 also counts as release).
+    Note,
+}
+
+fn default_midi_note() -> u8 {
+    60
+}
+
+fn default_midi_port_match() -> String {
+    "FS-1-WL".to_string()
+}
+
+fn default_midi_controller() -> u8 {
+    85
+}
+
+fn default_midi_press_value() -> u8 {
+    127
+}
+
+/// Audio cues when recording starts and stops.
+#[derive(Debug, Deserialize)]
+pub struct FeedbackConfig {
+    #[serde(default)]
+    pub sounds: bool,
+    /// Volume of the built-in tones, 0.0 - 1.0.
+    #[serde(default = "default_feedback_volume")]
+    pub volume: f32,
+    /// Sound file played on start instead of the built-in tone.
+    #[serde(default)]
+    pub start_sound: Option<PathBuf>,
+    /// Sound file played on stop instead of the built-in tone.
+    #[serde(default)]
+    pub stop_sound: Option<PathBuf>,
+}
+
+impl Default for FeedbackConfig {
+    fn default() -> Self {
+        Self {
+            sounds: false,
+            volume: default_feedback_volume(),
+            start_sound: None,
+            stop_sound: None,
+        }
+    }
+}
+
+fn default_feedback_volume() -> f32 {
+    0.2
+}
+
+/// How text reaches the focused application.
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct OutputConfig {
+    /// Type streaming partials into the focused window while speaking, instead
+    /// of pasting once on release.
+    #[serde(default)]
+    pub live_typing: bool,
+    /// Also copy the final text to the primary selection, for middle-click paste.
+    #[serde(default)]
+    pub primary_selection: bool,
+    /// Log the final text instead of pasting it; also turns off live typing.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// In a dry run, still copy the text to the clipboard.
+    #[serde(default)]
+    pub dry_run_clipboard: bool,
+}
+
+/// Overlay window settings. Read each time the overlay opens.
+#[derive(Debug, Deserialize, Clone)]
+pub struct OverlayConfig {
+    /// Layer-shell layer the overlay is drawn on.
+    #[serde(default)]
+    pub layer: OverlayLayer,
+    /// Let clicks pass through the panel to the window beneath; only the
+    /// cancel button takes pointer input. Otherwise the whole panel does.
+    #[serde(default = "default_true")]
+    pub click_through: bool,
+    /// Show an MM:SS elapsed-time readout in the recording panel.
+    #[serde(default)]
+    pub show_timer: bool,
+    /// Ease the recording panel toward the mouse cursor instead of keeping
+    /// it at a fixed spot on the output.
+    #[serde(default)]
+    pub follow_cursor: bool,
+    /// Words the server reports a confidence (0.0-1.0) below this for are
+    /// drawn dimmed. 0 disables dimming.
+    #[serde(default = "default_confidence_threshold")]
+    pub confidence_threshold: f32,
+}
+
+impl Default for OverlayConfig {
+    fn default() -> Self {
+        Self {
+            layer: OverlayLayer::default(),
+            click_through: true,
+            show_timer: false,
+            follow_cursor: false,
+            confidence_threshold: default_confidence_threshold(),
+        }
+    }
+}
+
+fn default_confidence_threshold() -> f32 {
+    0.5
+}
+
+/// Layer-shell layer for the overlay.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OverlayLayer {
+    /// Above everything, including fullscreen windows (and, on some
+    /// compositors, the lock screen).
+    #[default]
+    Overlay,
+    /// Above normal windows but below the overlay layer.
+    Top,
+}
+
+/// `[[app_overrides]]` entry: paste settings for windows of one class.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AppOverride {
+    /// Window class from `hyprctl activewindow`, case-insensitive. `"*"`
+    /// matches any window without a more specific entry.
+    pub class: String,
+    /// Force a paste method instead of the XWayland heuristic.
+    #[serde(default)]
+    pub method: Option<PasteMethod>,
+    /// Delay between typed keys, for apps that drop fast input.
+    #[serde(default)]
+    pub type_delay_ms: Option<u32>,
+    /// Shortcut sent with `method = "clipboard"`, e.g. `"ctrl+shift+v"`.
+    #[serde(default)]
+    pub paste_keys: Option<String>,
+}
+
+/// How text is delivered to the focused window.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PasteMethod {
+    /// Type via the Wayland virtual keyboard.
+    Wtype,
+    /// Type via X11, for XWayland clients.
+    Xdotool,
+    /// Copy to the clipboard and send a paste shortcut.
+    Clipboard,
+}
+
+/// Unix socket for scripted control. Read once at startup.
+#[derive(Debug, Deserialize, Default, Clone, PartialEq)]
+pub struct ControlConfig {
+    /// Listen for `start`/`stop`/`toggle`/`abort` commands.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Socket path; defaults to `$XDG_RUNTIME_DIR/justspeak.sock`.
+    #[serde(default)]
+    pub socket: Option<PathBuf>,
+}
+
+/// A `[replacements]` entry: either a plain string (case-insensitive whole-word
+/// match) or a table with `with` and `regex = true` for a regex pattern.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum Replacement {
+    Plain(String),
+    Detailed {
+        with: String,
+        #[serde(default)]
+        regex: bool,
+    },
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ServerConfig {
     #[serde(default = "default_server_url")]
     pub url: String,
+    /// Servers to try in priority order, falling over to the next when one is
+    /// unreachable. Replaces `url` when non-empty.
+    #[serde(default)]
+    pub urls: Vec<String>,
+    /// Extra attempts for a failed HTTP transcription (connection errors and 5xx only).
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// Backoff before the first retry; doubled on each subsequent attempt.
+    #[serde(default = "default_retry_base_ms")]
+    pub retry_base_ms: u64,
+    /// Time allowed for the server to respond to a transcription request.
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// What to do when streaming fails or returns no text.
+    #[serde(default)]
+    pub fallback: Fallback,
+    /// Response timeout for the fallback HTTP transcription. Defaults to
+    /// `timeout_ms`.
+    #[serde(default)]
+    pub fallback_timeout_ms: Option<u64>,
+    /// Time allowed to establish a TCP/TLS connection.
+    #[serde(default = "default_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+    /// While the server is down at startup, re-probe `/health` this often. 0 disables.
+    #[serde(default = "default_health_interval_ms")]
+    pub health_interval_ms: u64,
+    /// HTTP transcription path, joined to `url`. Defaults depend on `backend`.
+    #[serde(default)]
+    pub transcribe_path: Option<String>,
+    /// WebSocket streaming path, joined to `url`.
+    #[serde(default = "default_stream_path")]
+    pub stream_path: String,
+    /// How often captured audio is sent while streaming. Zero is rejected.
+    #[serde(default = "default_stream_interval_ms")]
+    pub stream_interval_ms: NonZeroU64,
+    /// Hold back audio until at least this many bytes are pending, so slow
+    /// links aren't sent tiny frames. 0 sends whatever is new on each tick.
+    #[serde(default)]
+    pub stream_min_bytes: usize,
+    /// Which server API to speak.
+    #[serde(default)]
+    pub backend: Backend,
+    /// Model name sent to OpenAI-compatible servers.
+    #[serde(default = "default_openai_model")]
+    pub model: String,
+    /// Sample encoding for streamed audio and uploaded WAV files.
+    #[serde(default)]
+    pub audio_format: AudioFormat,
+    /// Initial prompt biasing vocabulary and spelling (names, jargon). Sent
+    /// as the `prompt` field; not logged.
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// Bearer token, sent as `Authorization: Bearer <key>` on every request.
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Custom auth header name, for proxies that expect something other than a bearer token.
+    #[serde(default)]
+    pub auth_header: Option<String>,
+    /// Value for `auth_header`.
+    #[serde(default)]
+    pub auth_value: Option<String>,
+}
+
+/// Recovery when a streaming transcription fails, times out or comes back
+/// empty.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Fallback {
+    /// Upload the whole recording for HTTP transcription.
+    #[default]
+    Http,
+    /// Give up; nothing is pasted.
+    None,
+}
+
+/// Transcription server API.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// nemospeech: plain-text `/transcribe/` plus WebSocket streaming.
+    #[default]
+    Nemospeech,
+    /// OpenAI-style `/v1/audio/transcriptions` (whisper.cpp server, etc.). HTTP only.
+    Openai,
+}
+
+impl Backend {
+    /// Default HTTP transcription path for this API.
+    pub fn default_transcribe_path(self) -> &'static str {
+        match self {
+            Backend::Nemospeech => "/transcribe/",
+            Backend::Openai => "/v1/audio/transcriptions",
+        }
+    }
+}
+
+/// Sample encoding sent to the server.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    /// 16-bit signed little-endian PCM; understood by every backend.
+    #[default]
+    S16le,
+    /// 32-bit float little-endian, preserving the capture precision.
+    F32le,
+}
+
+impl AudioFormat {
+    /// Name used when declaring the format to the streaming endpoint.
+    pub fn name(self) -> &'static str {
+        match self {
+            AudioFormat::S16le => "s16le",
+            AudioFormat::F32le => "f32le",
+        }
+    }
+
+    /// Encoded size of one sample.
+    pub fn sample_bytes(self) -> usize {
+        match self {
+            AudioFormat::S16le => 2,
+            AudioFormat::F32le => 4,
+        }
+    }
 }
 
 impl Default for ServerConfig {
     fn default() -> Self {
         Self {
             url: DEFAULT_SERVER.to_string(),
+            urls: Vec::new(),
+            retries: DEFAULT_RETRIES,
+            retry_base_ms: DEFAULT_RETRY_BASE_MS,
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+            fallback: Fallback::default(),
+            fallback_timeout_ms: None,
+            connect_timeout_ms: DEFAULT_CONNECT_TIMEOUT_MS,
+            health_interval_ms: DEFAULT_HEALTH_INTERVAL_MS,
+            transcribe_path: None,
+            stream_path: DEFAULT_STREAM_PATH.to_string(),
+            stream_interval_ms: DEFAULT_STREAM_INTERVAL_MS,
+            stream_min_bytes: 0,
+            backend: Backend::default(),
+            model: DEFAULT_OPENAI_MODEL.to_string(),
+            audio_format: AudioFormat::default(),
+            prompt: None,
+            api_key: None,
+            auth_header: None,
+            auth_value: None,
         }
     }
 }
@@ -28,19 +580,77 @@ fn default_server_url() -> String {
     DEFAULT_SERVER.to_string()
 }
 
+fn default_true() -> bool {
+    true
+}
+
+fn default_stream_path() -> String {
+    DEFAULT_STREAM_PATH.to_string()
+}
+
+fn default_stream_interval_ms() -> NonZeroU64 {
+    DEFAULT_STREAM_INTERVAL_MS
+}
+
+fn default_timeout_ms() -> u64 {
+    DEFAULT_TIMEOUT_MS
+}
+
+fn default_connect_timeout_ms() -> u64 {
+    DEFAULT_CONNECT_TIMEOUT_MS
+}
+
+fn default_health_interval_ms() -> u64 {
+    DEFAULT_HEALTH_INTERVAL_MS
+}
+
+fn default_openai_model() -> String {
+    DEFAULT_OPENAI_MODEL.to_string()
+}
+
+fn default_retries() -> u32 {
+    DEFAULT_RETRIES
+}
+
+fn default_retry_base_ms() -> u64 {
+    DEFAULT_RETRY_BASE_MS
+}
+
+impl ServerConfig {
+    /// Auth headers to attach to HTTP and WebSocket requests.
+    pub fn auth_headers(&self) -> Vec<(String, String)> {
+        let mut headers = Vec::new();
+        if let Some(key) = &self.api_key {
+            headers.push(("Authorization".to_string(), format!("Bearer {key}")));
+        }
+        match (&self.auth_header, &self.auth_value) {
+            (Some(name), Some(value)) => headers.push((name.clone(), value.clone())),
+            (Some(_), None) | (None, Some(_)) => {
+                warn!("auth_header and auth_value must be set together, ignoring")
+            }
+            (None, None) => {}
+        }
+        headers
+    }
+}
+
 impl Config {
-    /// Load config with priority: CLI arg > env var > config file > default.
-    pub fn resolve_server_url(cli_server: Option<String>) -> String {
+    /// Resolve the server URLs, in priority order, from: CLI arg > env var >
+    /// config `urls` > config `url` > default. The CLI and env var name a
+    /// single server.
+    pub fn resolve_server_urls(&self, cli_server: Option<String>) -> Vec<String> {
         if let Some(url) = cli_server {
-            return url;
+            return vec![url];
         }
 
         if let Ok(url) = std::env::var("NEMOSPEECH_URL") {
-            return url;
+            return vec![url];
         }
 
-        let config = Self::load();
-        config.server.url
+        if !self.server.urls.is_empty() {
+            return self.server.urls.clone();
+        }
+        vec![self.server.url.clone()]
     }
 
     fn config_path() -> Option<PathBuf> {
@@ -51,31 +661,114 @@ impl Config {
             .map(|c| c.join("justspeak/config.toml"))
     }
 
-    fn load() -> Self {
+    /// Write the commented default config to the config path. Refuses to
+    /// overwrite an existing file unless `force` is set.
+    pub fn write_default(force: bool) -> Result<PathBuf> {
+        let path = Self::config_path()
+            .context("cannot determine config directory (neither XDG_CONFIG_HOME nor HOME set)")?;
+
+        if path.exists() && !force {
+            anyhow::bail!("{} already exists (use --force to overwrite)", path.display());
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        std::fs::write(&path, DEFAULT_CONFIG_TOML)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    pub fn load() -> Self {
+        match Self::reload() {
+            Ok(config) => config,
+            Err(e) => {
+                warn!(error = %e, "failed to load config, using defaults");
+                Self::default()
+            }
+        }
+    }
+
+    /// Read and parse the config file. Unlike `load`, errors are returned
+    /// rather than replaced with defaults, so a half-edited file doesn't
+    /// clobber a running configuration.
+    pub fn reload() -> Result<Self> {
         let Some(path) = Self::config_path() else {
-            return Self::default();
+            return Ok(Self::default());
         };
 
         if !path.exists() {
             debug!(path = %path.display(), "no config file found, using defaults");
-            return Self::default();
+            return Ok(Self::default());
         }
 
-        match std::fs::read_to_string(&path) {
-            Ok(contents) => match toml::from_str(&contents) {
-                Ok(config) => {
-                    debug!(path = %path.display(), "loaded config");
-                    config
-                }
-                Err(e) => {
-                    warn!(path = %path.display(), error = %e, "failed to parse config");
-                    Self::default()
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let config = toml::from_str(&contents)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        debug!(path = %path.display(), "loaded config");
+        Ok(config)
+    }
+
+    /// Names of changed sections that are only read at startup.
+    fn restart_required(&self, new: &Config) -> Vec<&'static str> {
+        let mut sections = Vec::new();
+        if self.input.undo_key != new.input.undo_key {
+            sections.push("input.undo_key");
+        }
+        if self.input.abort_key != new.input.abort_key {
+            sections.push("input.abort_key");
+        }
+        if self.input.pause_key != new.input.pause_key {
+            sections.push("input.pause_key");
+        }
+        if self.input.grab != new.input.grab {
+            sections.push("input.grab");
+        }
+        if self.midi != new.midi {
+            sections.push("midi");
+        }
+        if self.control != new.control {
+            sections.push("control");
+        }
+        sections
+    }
+
+    fn modified() -> Option<SystemTime> {
+        let path = Self::config_path()?;
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+}
+
+/// Watch the config file and reload it into `config` whenever its mtime
+/// changes, setting `changed` so the main loop can rebuild anything derived
+/// from it. Parse errors are logged and the previous config is kept.
+pub fn spawn_watcher(config: Arc<RwLock<Config>>, changed: Arc<AtomicBool>) {
+    tokio::spawn(async move {
+        let mut last_modified = Config::modified();
+        let mut interval = tokio::time::interval(RELOAD_POLL_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let modified = Config::modified();
+            if modified == last_modified {
+                continue;
+            }
+            last_modified = modified;
+
+            match Config::reload() {
+                Ok(new_config) => {
+                    info!("config file changed, reloading");
+                    let mut current = config.write().unwrap();
+                    for section in current.restart_required(&new_config) {
+                        warn!(section, "config section changed, restart justspeak to apply");
+                    }
+                    *current = new_config;
+                    changed.store(true, Ordering::Relaxed);
                 }
-            },
-            Err(e) => {
-                warn!(path = %path.display(), error = %e, "failed to read config");
-                Self::default()
+                Err(e) => warn!(error = %e, "config file changed but is invalid, keeping previous"),
             }
         }
-    }
+    });
 }
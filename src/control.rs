@@ -0,0 +1,138 @@
+use crate::config::ControlConfig;
+use crate::input::KeyEvent;
+use anyhow::{Context, Result};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+/// Commands accepted on the control socket, one per line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ControlCommand {
+    /// Start recording, as if the trigger key were pressed
+    Start,
+    /// Stop recording and transcribe, as if the trigger key were released
+    Stop,
+    /// Start if idle, stop if recording
+    Toggle,
+    /// Discard the current recording
+    Abort,
+}
+
+impl ControlCommand {
+    fn parse(line: &str) -> Option<Self> {
+        match line.trim() {
+            "start" => Some(Self::Start),
+            "stop" => Some(Self::Stop),
+            "toggle" => Some(Self::Toggle),
+            "abort" => Some(Self::Abort),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Stop => "stop",
+            Self::Toggle => "toggle",
+            Self::Abort => "abort",
+        }
+    }
+
+    fn key_event(self) -> KeyEvent {
+        match self {
+            Self::Start => KeyEvent::AltGrPressed,
+            Self::Stop => KeyEvent::AltGrReleased,
+            Self::Toggle => KeyEvent::Toggle,
+            Self::Abort => KeyEvent::Abort,
+        }
+    }
+}
+
+/// `socket` from config, or `$XDG_RUNTIME_DIR/justspeak.sock`.
+pub fn socket_path(config: &ControlConfig) -> PathBuf {
+    config.socket.clone().unwrap_or_else(|| {
+        std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir)
+            .join("justspeak.sock")
+    })
+}
+
+/// Bind the control socket and spawn a task that turns each command line
+/// into a KeyEvent on the shared trigger channel. Does nothing unless
+/// enabled. A socket file left over from a crashed instance is replaced; a
+/// live one is an error.
+pub fn spawn_listener(tx: mpsc::UnboundedSender<KeyEvent>, config: &ControlConfig) -> Result<()> {
+    if !config.enabled {
+        return Ok(());
+    }
+
+    let path = socket_path(config);
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+            anyhow::bail!("another justspeak is already listening on {}", path.display());
+        }
+        debug!(path = %path.display(), "removing stale control socket");
+        std::fs::remove_file(&path)
+            .with_context(|| format!("failed to remove stale socket {}", path.display()))?;
+    }
+
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("failed to bind control socket {}", path.display()))?;
+    info!(path = %path.display(), "listening for control commands");
+
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle_client(stream, tx.clone()));
+                }
+                Err(e) => warn!(error = %e, "control socket accept failed"),
+            }
+        }
+    });
+    Ok(())
+}
+
+async fn handle_client(stream: UnixStream, tx: mpsc::UnboundedSender<KeyEvent>) {
+    let (read, mut write) = stream.into_split();
+    let mut lines = tokio::io::BufReader::new(read).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let reply = match ControlCommand::parse(&line) {
+            Some(command) => {
+                debug!(command = command.name(), "control command");
+                if tx.send(command.key_event()).is_err() {
+                    return; // receiver dropped
+                }
+                "ok\n".to_string()
+            }
+            None => format!("error: unknown command {:?}\n", line.trim()),
+        };
+        if write.write_all(reply.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// `justspeak ctl <command>`: send one command to a running instance.
+pub fn send_command(config: &ControlConfig, command: ControlCommand) -> Result<()> {
+    let path = socket_path(config);
+    let mut stream = std::os::unix::net::UnixStream::connect(&path).with_context(|| {
+        format!(
+            "cannot connect to {} - is justspeak running with [control] enabled = true?",
+            path.display()
+        )
+    })?;
+    writeln!(stream, "{}", command.name())?;
+
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply)?;
+    match reply.trim() {
+        "ok" => Ok(()),
+        other => anyhow::bail!("justspeak replied: {other}"),
+    }
+}
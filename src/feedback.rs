@@ -0,0 +1,130 @@
+use crate::config::FeedbackConfig;
+use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+// Built-in tones: a short rising blip for start, a lower one for stop
+const START_TONE_HZ: f32 = 880.0;
+const STOP_TONE_HZ: f32 = 587.0;
+const TONE_DURATION: Duration = Duration::from_millis(70);
+/// Linear fade in/out to avoid clicks at the tone edges.
+const TONE_FADE_SECS: f32 = 0.01;
+
+/// Optional audio cues for recording start/stop. Each cue plays on its own
+/// thread so it never delays the recording path.
+pub struct Feedback {
+    enabled: bool,
+    volume: f32,
+    start_sound: Option<PathBuf>,
+    stop_sound: Option<PathBuf>,
+}
+
+impl Feedback {
+    pub fn new(config: &FeedbackConfig) -> Self {
+        Self {
+            enabled: config.sounds,
+            volume: config.volume.clamp(0.0, 1.0),
+            start_sound: config.start_sound.clone(),
+            stop_sound: config.stop_sound.clone(),
+        }
+    }
+
+    /// Cue for recording started.
+    pub fn start(&self) {
+        self.play(self.start_sound.clone(), START_TONE_HZ);
+    }
+
+    /// Cue for recording stopped.
+    pub fn stop(&self) {
+        self.play(self.stop_sound.clone(), STOP_TONE_HZ);
+    }
+
+    fn play(&self, file: Option<PathBuf>, tone_hz: f32) {
+        if !self.enabled {
+            return;
+        }
+        let volume = self.volume;
+        std::thread::spawn(move || {
+            let result = match &file {
+                Some(path) => play_file(path),
+                None => play_tone(tone_hz, volume),
+            };
+            if let Err(e) = result {
+                warn!(error = %e, "failed to play sound cue");
+            }
+        });
+    }
+}
+
+/// Play a sound file via PipeWire's `pw-play`, falling back to `paplay`.
+/// A player that is installed but fails (e.g. `pw-play` without a running
+/// PipeWire daemon) also falls through to the next one.
+fn play_file(path: &Path) -> Result<()> {
+    let mut failed = None;
+    for player in ["pw-play", "paplay"] {
+        match Command::new(player).arg(path).status() {
+            Ok(status) if status.success() => return Ok(()),
+            Ok(status) => {
+                debug!(player, %status, "audio player failed, trying the next one");
+                failed = Some(format!("{player} exited with status: {status}"));
+            }
+            Err(_) => continue, // not installed, try the next one
+        }
+    }
+    match failed {
+        Some(last) => anyhow::bail!("no audio player could play the file ({last})"),
+        None => anyhow::bail!("no audio player found (install pipewire or pulseaudio-utils)"),
+    }
+}
+
+/// Synthesize a short sine burst on the default output device.
+fn play_tone(freq: f32, volume: f32) -> Result<()> {
+    let host = cpal::default_host();
+    let device = host
+        .default_output_device()
+        .context("no output device available")?;
+    let supported = device
+        .default_output_config()
+        .context("no default output config")?;
+    if supported.sample_format() != cpal::SampleFormat::F32 {
+        anyhow::bail!("unsupported output sample format {:?}", supported.sample_format());
+    }
+
+    let config = supported.config();
+    let channels = config.channels as usize;
+    let rate = config.sample_rate.0 as f32;
+    let total = (TONE_DURATION.as_secs_f32() * rate) as usize;
+    let fade = (TONE_FADE_SECS * rate) as usize;
+    let mut n = 0usize;
+
+    let stream = device
+        .build_output_stream(
+            &config,
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                for frame in data.chunks_mut(channels) {
+                    let sample = if n < total {
+                        let envelope = (n.min(total - n) as f32 / fade as f32).min(1.0);
+                        (n as f32 / rate * freq * std::f32::consts::TAU).sin() * volume * envelope
+                    } else {
+                        0.0
+                    };
+                    frame.fill(sample);
+                    n += 1;
+                }
+            },
+            move |err| {
+                warn!(error = %err, "sound cue stream error");
+            },
+            None,
+        )
+        .context("failed to build output stream")?;
+
+    stream.play().context("failed to start output stream")?;
+    // Let the tone and the device buffer drain before dropping the stream
+    std::thread::sleep(TONE_DURATION + Duration::from_millis(100));
+    debug!(freq, "played sound cue");
+    Ok(())
+}
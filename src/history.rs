@@ -0,0 +1,61 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+/// Append-only log of successful transcriptions, one JSON object per line, so
+/// text dictated into the wrong window can be recovered.
+pub struct History {
+    path: Option<PathBuf>,
+}
+
+impl History {
+    /// A disabled history records nothing.
+    pub fn new(enabled: bool) -> Self {
+        let path = if enabled { Self::history_path() } else { None };
+        match &path {
+            Some(p) => debug!(path = %p.display(), "transcription history enabled"),
+            None => debug!("transcription history disabled"),
+        }
+        Self { path }
+    }
+
+    fn history_path() -> Option<PathBuf> {
+        std::env::var("XDG_STATE_HOME")
+            .map(PathBuf::from)
+            .or_else(|_| std::env::var("HOME").map(|h| PathBuf::from(h).join(".local/state")))
+            .ok()
+            .map(|s| s.join("justspeak/history.jsonl"))
+    }
+
+    /// Append an entry. Failures are logged and otherwise ignored so history
+    /// never gets in the way of pasting.
+    pub fn record(&self, duration_secs: f32, text: &str) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Err(e) = Self::append(path, duration_secs, text) {
+            warn!(path = %path.display(), error = %e, "failed to write transcription history");
+        }
+    }
+
+    fn append(path: &Path, duration_secs: f32, text: &str) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let entry = serde_json::json!({
+            "timestamp": timestamp,
+            "duration_secs": duration_secs,
+            "text": text,
+        });
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        writeln!(file, "{entry}")
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::process::Command;
+use tracing::debug;
+
+/// Where to assume the cursor is when Hyprland can't be asked.
+pub const FALLBACK_CURSOR: (f32, f32) = (960.0, 800.0);
+
+/// `hyprctl cursorpos -j`
+#[derive(Debug, Deserialize)]
+struct CursorPos {
+    x: f32,
+    y: f32,
+}
+
+/// The focused window, from `hyprctl activewindow -j`. Fields are empty when
+/// nothing is focused (hyprctl prints `{}`).
+#[derive(Debug, Default, Deserialize)]
+pub struct ActiveWindow {
+    #[serde(default)]
+    pub class: String,
+    #[serde(default)]
+    pub xwayland: bool,
+}
+
+/// Global cursor position in logical pixels, or `None` if hyprctl is
+/// unavailable.
+pub fn cursor_position() -> Option<(f32, f32)> {
+    match query::<CursorPos>(&["cursorpos", "-j"]) {
+        Ok(pos) => Some((pos.x, pos.y)),
+        Err(e) => {
+            debug!(error = %e, "cursor position unavailable");
+            None
+        }
+    }
+}
+
+/// The focused window's class and whether it is an XWayland client.
+pub fn active_window() -> Result<ActiveWindow> {
+    query(&["activewindow", "-j"])
+}
+
+/// Run hyprctl with `args` and parse its JSON output.
+fn query<T: DeserializeOwned>(args: &[&str]) -> Result<T> {
+    let output = Command::new("hyprctl")
+        .args(args)
+        .output()
+        .context("failed to run hyprctl")?;
+    if !output.status.success() {
+        anyhow::bail!("hyprctl {} exited with status: {}", args.join(" "), output.status);
+    }
+    parse(&output.stdout)
+}
+
+/// Parse hyprctl's JSON output, ignoring fields `T` doesn't name.
+fn parse<T: DeserializeOwned>(stdout: &[u8]) -> Result<T> {
+    serde_json::from_slice(stdout).context("failed to parse hyprctl output")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_position_with_negative_coordinate() {
+        let pos: CursorPos = parse(br#"{"x": 100, "y": -5}"#).unwrap();
+        assert_eq!((pos.x, pos.y), (100.0, -5.0));
+    }
+
+    #[test]
+    fn full_active_window() {
+        let json = br#"{
+            "address": "0x5d4e5a8f3c40",
+            "mapped": true,
+            "hidden": false,
+            "at": [-1920, 44],
+            "size": [1904, 1026],
+            "workspace": {"id": 2, "name": "2"},
+            "floating": false,
+            "pseudo": false,
+            "monitor": 1,
+            "class": "code-oss",
+            "title": "main.rs - \"x\": 1 {at} - Code - OSS",
+            "initialClass": "code-oss",
+            "initialTitle": "Code - OSS",
+            "pid": 4242,
+            "xwayland": true,
+            "pinned": false,
+            "fullscreen": 0,
+            "fullscreenClient": 0,
+            "grouped": [],
+            "tags": [],
+            "swallowing": "0x0",
+            "focusHistoryID": 0,
+            "inhibitingIdle": false,
+            "x": {"at": [1, 2]}
+        }"#;
+        let window: ActiveWindow = parse(json).unwrap();
+        assert_eq!(window.class, "code-oss");
+        assert!(window.xwayland);
+    }
+
+    #[test]
+    fn no_focused_window() {
+        let window: ActiveWindow = parse(b"{}").unwrap();
+        assert_eq!(window.class, "");
+        assert!(!window.xwayland);
+    }
+
+    #[test]
+    fn invalid_output_is_an_error() {
+        assert!(parse::<CursorPos>(b"invalid request").is_err());
+    }
+}
@@ -1,6 +1,9 @@
+use crate::config::InputConfig;
 use anyhow::Result;
+use evdev::uinput::{VirtualDevice, VirtualDeviceBuilder};
 use evdev::{Device, InputEventKind, Key};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use tokio::sync::mpsc;
 use tracing::{debug, info, warn};
 
@@ -8,6 +11,120 @@ use tracing::{debug, info, warn};
 pub enum KeyEvent {
     AltGrPressed,
     AltGrReleased,
+    /// Remove the most recently pasted text.
+    Undo,
+    /// Discard the current recording without transcribing.
+    Abort,
+    /// Pause or resume the current recording.
+    PauseToggle,
+    /// Start recording if idle, stop if recording (control socket).
+    Toggle,
+    /// Ctrl-C: abandon any recording and exit.
+    Shutdown,
+}
+
+/// Keys the listener reacts to, resolved from config.
+#[derive(Debug, Clone, Copy)]
+struct Bindings {
+    undo: Option<Key>,
+    abort: Option<Key>,
+    pause: Option<Key>,
+}
+
+impl Bindings {
+    fn from_config(config: &InputConfig) -> Self {
+        Self {
+            undo: config.undo_key.as_deref().and_then(parse_key),
+            abort: config.abort_key.as_deref().and_then(parse_key),
+            pause: config.pause_key.as_deref().and_then(parse_key),
+        }
+    }
+
+    /// Map a key press/release to an event. Repeats (value=2) are ignored.
+    fn map(&self, key: Key, value: i32) -> Option<KeyEvent> {
+        match (key, value) {
+            (Key::KEY_RIGHTALT, 1) => Some(KeyEvent::AltGrPressed),
+            (Key::KEY_RIGHTALT, 0) => Some(KeyEvent::AltGrReleased),
+            (k, 1) if Some(k) == self.undo => Some(KeyEvent::Undo),
+            (k, 1) if Some(k) == self.abort => Some(KeyEvent::Abort),
+            (k, 1) if Some(k) == self.pause => Some(KeyEvent::PauseToggle),
+            _ => None,
+        }
+    }
+}
+
+fn parse_key(name: &str) -> Option<Key> {
+    match Key::from_str(name) {
+        Ok(key) => Some(key),
+        Err(_) => {
+            warn!(key = name, "unknown key name (expected e.g. KEY_F9), ignoring");
+            None
+        }
+    }
+}
+
+/// A keyboard being listened to. With `[input] grab` it is grabbed and its
+/// events reach other apps only through `forward`.
+struct Keyboard {
+    // Dropped first: closing the device releases the grab before the virtual
+    // keyboard goes away.
+    device: Device,
+    forward: Option<VirtualDevice>,
+}
+
+impl Keyboard {
+    fn open(path: &Path, grab: bool) -> std::io::Result<Self> {
+        let mut device = Device::open(path)?;
+        let forward = if grab {
+            match grab_device(&mut device) {
+                Ok(forward) => {
+                    info!(path = %path.display(), "grabbed keyboard, AltGr no longer reaches other apps");
+                    Some(forward)
+                }
+                Err(e) => {
+                    warn!(path = %path.display(), error = %e,
+                        "failed to grab keyboard (is /dev/uinput writable?), AltGr will also reach other apps");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        Ok(Self { device, forward })
+    }
+}
+
+impl Drop for Keyboard {
+    fn drop(&mut self) {
+        // The kernel also releases the grab when the device is closed, which
+        // covers crashes and exits; this just makes a listener thread that
+        // stops on its own give the keyboard back right away.
+        if self.forward.is_some() {
+            let _ = self.device.ungrab();
+        }
+    }
+}
+
+/// Grab `device` and create a virtual keyboard with the same keys to re-emit
+/// everything but AltGr on.
+fn grab_device(device: &mut Device) -> std::io::Result<VirtualDevice> {
+    let mut builder = VirtualDeviceBuilder::new()?.name("justspeak virtual keyboard");
+    if let Some(keys) = device.supported_keys() {
+        builder = builder.with_keys(keys)?;
+    }
+    if let Some(axes) = device.supported_relative_axes() {
+        builder = builder.with_relative_axes(axes)?;
+    }
+    let forward = builder.build()?;
+
+    // A key held while grabbing would never be seen released by the
+    // compositor and repeat forever (e.g. Enter after starting justspeak from
+    // a terminal), so wait until nothing is pressed.
+    while device.get_key_state().is_ok_and(|keys| keys.iter().next().is_some()) {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    device.grab()?;
+    Ok(forward)
 }
 
 /// Find all keyboard devices in /dev/input/
@@ -37,15 +154,17 @@ fn find_keyboards() -> Result<Vec<PathBuf>> {
 }
 
 /// Spawn a blocking thread that reads evdev events and sends AltGr press/release
-/// over a channel. Returns immediately.
-pub fn spawn_listener(tx: mpsc::UnboundedSender<KeyEvent>) -> Result<()> {
+/// (plus any configured extra keys) over a channel. Returns immediately.
+pub fn spawn_listener(tx: mpsc::UnboundedSender<KeyEvent>, config: &InputConfig) -> Result<()> {
     let keyboards = find_keyboards()?;
+    let bindings = Bindings::from_config(config);
+    let grab = config.grab;
 
     for path in keyboards {
         let tx = tx.clone();
         std::thread::spawn(move || {
-            let mut device = match Device::open(&path) {
-                Ok(d) => d,
+            let mut keyboard = match Keyboard::open(&path, grab) {
+                Ok(k) => k,
                 Err(e) => {
                     warn!(path = %path.display(), error = %e, "failed to open device");
                     return;
@@ -53,23 +172,36 @@ pub fn spawn_listener(tx: mpsc::UnboundedSender<KeyEvent>) -> Result<()> {
             };
             info!(path = %path.display(), "listening for AltGr on device");
             loop {
-                match device.fetch_events() {
+                match keyboard.device.fetch_events() {
                     Ok(events) => {
+                        // Events to pass on when grabbed; emit() adds the SYN_REPORT
+                        let mut passthrough = Vec::new();
                         for ev in events {
-                            if let InputEventKind::Key(Key::KEY_RIGHTALT) = ev.kind() {
-                                let event = match ev.value() {
-                                    1 => Some(KeyEvent::AltGrPressed),
-                                    0 => Some(KeyEvent::AltGrReleased),
-                                    _ => None, // repeat events (value=2) ignored
-                                };
-                                if let Some(event) = event {
-                                    debug!(?event, "key event");
-                                    if tx.send(event).is_err() {
-                                        return; // receiver dropped
-                                    }
+                            let key = match ev.kind() {
+                                InputEventKind::Key(key) => key,
+                                InputEventKind::RelAxis(_) => {
+                                    passthrough.push(ev);
+                                    continue;
+                                }
+                                _ => continue,
+                            };
+                            if key != Key::KEY_RIGHTALT {
+                                passthrough.push(ev);
+                            }
+                            if let Some(event) = bindings.map(key, ev.value()) {
+                                debug!(?event, "key event");
+                                if tx.send(event).is_err() {
+                                    return; // receiver dropped
                                 }
                             }
                         }
+                        let forwarded = match &mut keyboard.forward {
+                            Some(forward) if !passthrough.is_empty() => forward.emit(&passthrough),
+                            _ => Ok(()),
+                        };
+                        if let Err(e) = forwarded {
+                            warn!(error = %e, "failed to pass keys on to the virtual keyboard");
+                        }
                     }
                     Err(e) => {
                         warn!(error = %e, "error reading events, retrying");
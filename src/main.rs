@@ -1,21 +1,33 @@
 mod audio;
 mod config;
+mod control;
+mod feedback;
+mod history;
+mod hyprctl;
 mod input;
 mod midi;
 mod overlay;
 mod paste;
+mod text;
 mod transcribe;
 
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use futures_util::{SinkExt, StreamExt};
 use input::KeyEvent;
-use overlay::OverlayCommand;
-use std::sync::Arc;
+use overlay::{LinkStatus, OverlayCommand};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
 use tokio::sync::mpsc;
 use tokio_tungstenite::tungstenite::Message;
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
+
+/// How many times a dropped streaming connection is re-established per recording.
+const MAX_STREAM_RECONNECTS: u32 = 2;
+const STREAM_RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_millis(250);
 
 #[derive(Parser)]
 #[command(name = "justspeak", about = "Voice transcription for Wayland")]
@@ -27,6 +39,56 @@ struct Args {
     /// Disable the fly-in overlay animation
     #[arg(long)]
     no_overlay: bool,
+
+    /// Don't record transcriptions to the local history log
+    #[arg(long)]
+    no_history: bool,
+
+    /// Show the overlay and log the transcription, but don't paste it
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Write a commented default config.toml and exit
+    #[arg(long)]
+    init_config: bool,
+
+    /// With --init-config, overwrite an existing config file
+    #[arg(long, requires = "init_config")]
+    force: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Send a command to the running instance over the control socket
+    Ctl {
+        #[arg(value_enum)]
+        command: control::ControlCommand,
+    },
+    /// Transcribe an audio file and print the text to stdout
+    Transcribe {
+        /// Audio file, or `-` for stdin. Non-WAV formats need ffmpeg.
+        file: PathBuf,
+
+        /// Print timed segments instead of plain text (`--timestamps=srt` for subtitles)
+        #[arg(
+            long,
+            value_enum,
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "json"
+        )]
+        timestamps: Option<TimestampFormat>,
+    },
+}
+
+/// Output format for `transcribe --timestamps`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum TimestampFormat {
+    Json,
+    Srt,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -37,7 +99,9 @@ enum State {
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    // Logs go to stderr so `transcribe` output can be piped
     tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
         .with_env_filter(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
@@ -46,117 +110,273 @@ async fn main() -> Result<()> {
 
     let args = Args::parse();
 
+    if args.init_config {
+        let path = config::Config::write_default(args.force)?;
+        println!("wrote default config to {}", path.display());
+        return Ok(());
+    }
+
+    match &args.command {
+        Some(Command::Transcribe { file, timestamps }) => {
+            return transcribe_file(file, *timestamps, args.server.clone());
+        }
+        Some(Command::Ctl { command }) => {
+            return control::send_command(&config::Config::load().control, *command);
+        }
+        None => {}
+    }
+
     // Preflight checks
     paste::check_wtype()?;
-    let transcriber = Arc::new(transcribe::Transcriber::new(args.server));
+    if args.dry_run {
+        info!("dry run: transcriptions are logged, not pasted");
+    }
+    let config = Arc::new(RwLock::new(config::Config::load()));
+    let config_changed = Arc::new(AtomicBool::new(false));
+    let (mut transcriber, mut postprocessor, mut history, mut feedback) = {
+        let config = config.read().unwrap();
+        (
+            Arc::new(transcribe::Transcriber::new(&config, args.server.clone())),
+            text::PostProcessor::new(&config),
+            history::History::new(config.history.enabled && !args.no_history),
+            feedback::Feedback::new(&config.feedback),
+        )
+    };
+    transcribe::spawn_health_probe(
+        &transcriber,
+        std::time::Duration::from_millis(config.read().unwrap().server.health_interval_ms),
+    );
+    config::spawn_watcher(config.clone(), config_changed.clone());
     let audio = audio::AudioCapture::new()?;
     let audio_handle = audio.buffer_handle();
 
     info!("justspeak ready - hold Right Alt (AltGr) or MIDI foot pedal to speak");
 
     // Trigger event channel (keyboard and MIDI share the same channel)
-    let (tx, mut rx) = mpsc::unbounded_channel();
-    input::spawn_listener(tx.clone())?;
-    midi::spawn_listener(tx);
+    let (tx, rx) = mpsc::unbounded_channel();
+    input::spawn_listener(tx.clone(), &config.read().unwrap().input)?;
+    midi::spawn_listener(tx.clone(), config.read().unwrap().midi.clone());
+    let live_overlay = LiveOverlay::default();
+    let shutdown = Arc::new(AtomicBool::new(false));
+    spawn_signal_handler(tx.clone(), live_overlay.clone(), shutdown.clone());
+    if let Err(e) = control::spawn_listener(tx, &config.read().unwrap().control) {
+        warn!(error = %e, "control socket disabled");
+    }
 
     let mut state = State::Idle;
+    // Set by --no-overlay, or once the overlay turns out to be unavailable
+    let mut no_overlay = args.no_overlay;
+    // The current recording is paused: capture runs, but samples are dropped
+    let mut paused = false;
+    // Text inserted by the most recent paste, for undo
+    let mut last_paste: Option<String> = None;
+    let mut timings = Timings::start();
 
-    while let Some(event) = rx.recv().await {
+    let mut rx = Debounced::new(rx, &config.read().unwrap().input);
+
+    'events: while let Some(event) = rx.recv().await {
         match (state, event) {
-            (State::Idle, KeyEvent::AltGrPressed) => {
+            (_, KeyEvent::Shutdown) => break,
+
+            (State::Idle, KeyEvent::AltGrPressed | KeyEvent::Toggle) => {
+                // Pick up live config edits between dictations
+                if config_changed.swap(false, Ordering::Relaxed) {
+                    let config = config.read().unwrap();
+                    let reloaded = transcribe::Transcriber::new(&config, args.server.clone());
+                    reloaded.keep_server_of(&transcriber);
+                    transcriber = Arc::new(reloaded);
+                    // Re-check in the background; the old probe stops once
+                    // the previous transcriber is dropped
+                    transcribe::spawn_health_probe(
+                        &transcriber,
+                        std::time::Duration::from_millis(config.server.health_interval_ms),
+                    );
+                    postprocessor = text::PostProcessor::new(&config);
+                    history = history::History::new(config.history.enabled && !args.no_history);
+                    feedback = feedback::Feedback::new(&config.feedback);
+                    rx.set_window(&config.input);
+                    info!("applied reloaded config");
+                }
+
                 audio.start_recording();
+                paused = false;
+                timings = Timings::start();
+                feedback.start();
 
-                if !args.no_overlay {
+                if !no_overlay {
                     // Spawn overlay thread
-                    let overlay_handle = match overlay::spawn_overlay() {
+                    let overlay_config = config.read().unwrap().overlay.clone();
+                    let overlay_handle = match overlay::spawn_overlay(&overlay_config) {
                         Ok(h) => h,
                         Err(e) => {
-                            warn!(error = %e, "failed to spawn overlay");
+                            // Won't work next time either; record this one
+                            // and the rest as if --no-overlay had been given
+                            warn!(error = %e, "failed to spawn overlay, continuing without it");
+                            no_overlay = true;
                             state = State::Recording;
                             continue;
                         }
                     };
+                    *live_overlay.lock().unwrap() = Some(overlay_handle.tx.clone());
 
                     // Spawn streaming transcription task
                     let stop_flag = Arc::new(AtomicBool::new(false));
                     let stop_clone = stop_flag.clone();
                     let audio_handle_clone = audio_handle.clone();
                     let overlay_tx = overlay_handle.tx.clone();
-                    let ws_url = transcriber.ws_url();
-
-                    let stream_task = tokio::spawn(async move {
-                        streaming_transcription(
-                            stop_clone,
-                            audio_handle_clone,
-                            ws_url,
-                            overlay_tx,
-                        )
-                        .await
+                    let stream_transcriber = transcriber.clone();
+                    let first_partial = timings.first_partial.clone();
+
+                    // Live typing needs partials, so it only applies to streaming backends
+                    let (live_typing, paste_options) = {
+                        let config = config.read().unwrap();
+                        (config.output.live_typing, paste_options(&config, &args))
+                    };
+                    let live_typer = (live_typing
+                        && transcriber.supports_streaming()
+                        && !paste_options.dry_run)
+                        .then(|| paste::LiveTyper::spawn(paste_options.clone()));
+                    let partials = live_typer.as_ref().map(|t| t.partials());
+
+                    // Backends without streaming go straight to the HTTP path on release
+                    let stream_task = transcriber.supports_streaming().then(|| {
+                        tokio::spawn(async move {
+                            streaming_transcription(
+                                stop_clone,
+                                audio_handle_clone,
+                                stream_transcriber,
+                                overlay_tx,
+                                partials,
+                                first_partial,
+                            )
+                            .await
+                        })
                     });
 
-                    // Wait for AltGr release
-                    loop {
+                    // Wait for AltGr release (or abort)
+                    let stop_event = loop {
                         match rx.recv().await {
-                            Some(KeyEvent::AltGrReleased) => break,
+                            Some(
+                                event @ (KeyEvent::AltGrReleased
+                                | KeyEvent::Toggle
+                                | KeyEvent::Abort
+                                | KeyEvent::Shutdown),
+                            ) => break event,
+                            Some(KeyEvent::PauseToggle) => {
+                                paused = !paused;
+                                audio.set_paused(paused);
+                                overlay_handle.send(OverlayCommand::Paused(paused));
+                            }
                             Some(KeyEvent::AltGrPressed) => continue, // repeat
+                            Some(KeyEvent::Undo) => continue,
                             None => return Ok(()),
                         }
+                    };
+
+                    if matches!(stop_event, KeyEvent::Abort | KeyEvent::Shutdown) {
+                        // Drop the stream without setting the stop flag, so no
+                        // "done" is sent and no final text comes back
+                        if let Some(task) = stream_task {
+                            task.abort();
+                        }
+                        audio.stop_recording();
+                        feedback.stop();
+                        erase_live_typing(live_typer);
+                        overlay_handle.send(OverlayCommand::Close);
+                        close_overlay(overlay_handle, &live_overlay);
+                        info!("recording aborted");
+                        if stop_event == KeyEvent::Shutdown {
+                            break 'events;
+                        }
+                        state = State::Idle;
+                        continue;
                     }
 
+                    timings.released = Some(Instant::now());
+                    feedback.stop();
+
                     // Signal streaming to finish (it will send final chunk + "done")
                     stop_flag.store(true, Ordering::Relaxed);
 
-                    // Wait for streaming task to get final result from server
-                    let stream_result = tokio::time::timeout(
-                        std::time::Duration::from_secs(15),
-                        stream_task,
-                    )
-                    .await;
+                    // Wait for streaming task to get final result from server.
+                    // On timeout it's aborted, so a late final can't arrive
+                    // alongside the fallback.
+                    let stream_result = match stream_task {
+                        Some(task) => {
+                            let _abort = AbortOnDrop(task.abort_handle());
+                            Some(
+                                tokio::time::timeout(std::time::Duration::from_secs(15), task)
+                                    .await,
+                            )
+                        }
+                        None => None,
+                    };
 
                     // Now stop recording
                     let samples = audio.stop_recording();
                     let duration = samples.len() as f32 / 16_000.0;
 
-                    if duration < 0.3 {
-                        warn!(duration, "recording too short, ignoring");
+                    if too_short(duration, &config) {
+                        erase_live_typing(live_typer);
                         overlay_handle.send(OverlayCommand::Close);
-                        overlay_handle.join();
+                        close_overlay(overlay_handle, &live_overlay);
                         state = State::Idle;
                         continue;
                     }
 
                     // Extract final text from streaming, fall back to HTTP
-                    let final_text = match stream_result {
-                        Ok(Ok(Ok(text))) if !text.is_empty() => {
-                            info!(text = %text, "streaming transcription complete");
-                            text
+                    let (final_text, confidence) = match stream_result {
+                        Some(Ok(Ok(Ok(streamed)))) if !streamed.text.is_empty() => {
+                            info!(text = %streamed.text, "streaming transcription complete");
+                            (streamed.text, streamed.confidence)
                         }
                         other => {
                             match &other {
-                                Err(_) => warn!("streaming transcription timed out"),
-                                Ok(Err(e)) => warn!(error = %e, "streaming task panicked"),
-                                Ok(Ok(Err(e))) => {
+                                None => info!("backend does not stream, using HTTP transcription"),
+                                Some(Err(_)) => warn!("streaming transcription timed out"),
+                                Some(Ok(Err(e))) => warn!(error = %e, "streaming task panicked"),
+                                Some(Ok(Ok(Err(e)))) => {
                                     warn!(error = %e, "streaming transcription failed")
                                 }
                                 _ => warn!("streaming returned empty text"),
                             }
-                            info!("falling back to HTTP transcription");
+                            let fallback = config.read().unwrap().server.fallback;
+                            if other.is_some() && fallback == config::Fallback::None {
+                                info!("not re-transcribing over HTTP ([server] fallback = \"none\")");
+                                erase_live_typing(live_typer);
+                                overlay_handle.send(OverlayCommand::Close);
+                                close_overlay(overlay_handle, &live_overlay);
+                                state = State::Idle;
+                                continue;
+                            }
+                            if other.is_some() {
+                                info!("falling back to HTTP transcription");
+                            }
 
                             let tmp =
                                 tempfile::Builder::new().suffix(".wav").tempfile()?;
                             let wav_path = tmp.path().to_path_buf();
-                            audio::AudioCapture::write_wav(&samples, &wav_path)?;
-                            match transcriber.transcribe(&wav_path) {
-                                Ok(text) => text,
+                            audio::AudioCapture::write_wav(
+                                &samples,
+                                &wav_path,
+                                transcriber.audio_format(),
+                            )?;
+                            let transcription =
+                                transcribe_blocking(&transcriber, &wav_path, other.is_some())
+                                    .await;
+                            match transcription {
+                                Ok(text) => (text, None),
                                 Err(e) => {
                                     warn!(error = %e, "fallback transcription failed");
+                                    erase_live_typing(live_typer);
                                     overlay_handle.send(OverlayCommand::UpdateText(
                                         "Transcription server unreachable".into(),
+                                        None,
                                     ));
                                     tokio::time::sleep(std::time::Duration::from_secs(2))
                                         .await;
                                     overlay_handle.send(OverlayCommand::Close);
-                                    overlay_handle.join();
+                                    close_overlay(overlay_handle, &live_overlay);
                                     state = State::Idle;
                                     continue;
                                 }
@@ -164,30 +384,57 @@ async fn main() -> Result<()> {
                         }
                     };
 
+                    timings.final_text = Some(Instant::now());
+
                     // Check if user cancelled via overlay button
                     if overlay_handle.was_cancelled() {
                         info!("transcription cancelled by user");
-                        overlay_handle.join();
+                        erase_live_typing(live_typer);
+                        close_overlay(overlay_handle, &live_overlay);
                         state = State::Idle;
                         continue;
                     }
 
+                    // Ctrl-C while waiting on the server: the overlay is
+                    // already gone, so don't paste behind the user's back
+                    if shutdown.load(Ordering::Relaxed) {
+                        info!("discarding transcription, shutting down");
+                        erase_live_typing(live_typer);
+                        close_overlay(overlay_handle, &live_overlay);
+                        break 'events;
+                    }
+
+                    let final_text = postprocessor.apply(&final_text, &samples);
                     if final_text.is_empty() {
                         warn!("final transcription returned empty text");
+                        erase_live_typing(live_typer);
                         overlay_handle.send(OverlayCommand::Close);
-                        overlay_handle.join();
+                        close_overlay(overlay_handle, &live_overlay);
                         state = State::Idle;
                         continue;
                     }
 
-                    let (cx, cy) = get_cursor_position();
+                    history.record(duration, &final_text);
+
+                    // Post-processing can merge or drop words; confidence only
+                    // still lines up if the word count is unchanged
+                    let confidence = confidence
+                        .filter(|c| c.len() == final_text.split_whitespace().count());
+                    let (cx, cy) = hyprctl::cursor_position().unwrap_or(hyprctl::FALLBACK_CURSOR);
                     overlay_handle
-                        .send(OverlayCommand::Finish(final_text.clone(), cx, cy));
-                    overlay_handle.join();
+                        .send(OverlayCommand::Finish(final_text.clone(), confidence, cx, cy));
+                    close_overlay(overlay_handle, &live_overlay);
 
-                    if let Err(e) = paste::paste_text(&final_text) {
-                        error!(error = %e, "failed to paste");
+                    // Live typing already put most of the text in place; just correct it
+                    let pasted = match live_typer {
+                        Some(typer) => typer.finish(&final_text),
+                        None => paste::paste_text(&final_text, &paste_options),
+                    };
+                    match pasted {
+                        Ok(()) => last_paste = Some(final_text),
+                        Err(e) => error!(error = %e, "failed to paste"),
                     }
+                    timings.log(duration);
 
                     state = State::Idle;
                 } else {
@@ -196,28 +443,40 @@ async fn main() -> Result<()> {
                 }
             }
 
-            (State::Recording, KeyEvent::AltGrReleased) if args.no_overlay => {
+            (State::Recording, KeyEvent::AltGrReleased | KeyEvent::Toggle) if no_overlay => {
                 let samples = audio.stop_recording();
+                timings.released = Some(Instant::now());
+                feedback.stop();
                 let duration = samples.len() as f32 / 16_000.0;
 
-                if duration < 0.3 {
-                    warn!(duration, "recording too short, ignoring");
+                if too_short(duration, &config) {
                     state = State::Idle;
                     continue;
                 }
 
                 let tmp = tempfile::Builder::new().suffix(".wav").tempfile()?;
                 let wav_path = tmp.path().to_path_buf();
-                audio::AudioCapture::write_wav(&samples, &wav_path)?;
+                audio::AudioCapture::write_wav(&samples, &wav_path, transcriber.audio_format())?;
 
-                match transcriber.transcribe(&wav_path) {
+                let result = transcribe_blocking(&transcriber, &wav_path, false).await;
+                timings.final_text = Some(Instant::now());
+                if shutdown.load(Ordering::Relaxed) {
+                    info!("discarding transcription, shutting down");
+                    break 'events;
+                }
+                match result {
                     Ok(text) if text.is_empty() => {
                         warn!("transcription returned empty text");
                     }
                     Ok(text) => {
-                        if let Err(e) = paste::paste_text(&text) {
-                            error!(error = %e, "failed to paste");
+                        let text = postprocessor.apply(&text, &samples);
+                        history.record(duration, &text);
+                        let options = paste_options(&config.read().unwrap(), &args);
+                        match paste::paste_text(&text, &options) {
+                            Ok(()) => last_paste = Some(text),
+                            Err(e) => error!(error = %e, "failed to paste"),
                         }
+                        timings.log(duration);
                     }
                     Err(e) => {
                         error!(error = %e, "transcription failed");
@@ -227,45 +486,293 @@ async fn main() -> Result<()> {
                 state = State::Idle;
             }
 
+            (State::Idle, KeyEvent::Undo) => match last_paste.take() {
+                // Newlines were sent as Enter, so every char is one backspace
+                Some(text) => {
+                    let options = paste_options(&config.read().unwrap(), &args);
+                    if let Err(e) = paste::backspace(text.chars().count(), &options) {
+                        error!(error = %e, "failed to undo paste");
+                    }
+                }
+                None => info!("nothing to undo"),
+            },
+
+            (State::Recording, KeyEvent::Abort) => {
+                audio.stop_recording();
+                feedback.stop();
+                info!("recording aborted");
+                state = State::Idle;
+            }
+
+            // Only reached without the overlay, which handles it while recording
+            (State::Recording, KeyEvent::PauseToggle) => {
+                paused = !paused;
+                audio.set_paused(paused);
+            }
+
             // Ignore spurious events
+            (State::Idle, KeyEvent::Abort | KeyEvent::PauseToggle) => {}
+            (State::Recording, KeyEvent::Undo) => {}
             (State::Idle, KeyEvent::AltGrReleased) => {}
             (State::Recording, KeyEvent::AltGrPressed) => {} // repeat
             (State::Recording, KeyEvent::AltGrReleased) => {} // handled in overlay branch above
+            (State::Recording, KeyEvent::Toggle) => {} // likewise
         }
     }
 
+    if state == State::Recording {
+        audio.stop_recording();
+    }
+    info!("shut down");
     Ok(())
 }
 
+/// Command sender of the overlay currently on screen, if any.
+type LiveOverlay = Arc<Mutex<Option<std::sync::mpsc::Sender<OverlayCommand>>>>;
+
+/// HTTP transcription on the blocking pool, so retries and their backoff
+/// don't stall the event loop (overlay updates, keys, Ctrl-C). `fallback`
+/// uses the shorter timeout for re-transcribing after a failed stream.
+async fn transcribe_blocking(
+    transcriber: &Arc<transcribe::Transcriber>,
+    wav_path: &Path,
+    fallback: bool,
+) -> Result<String> {
+    let transcriber = transcriber.clone();
+    let wav_path = wav_path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        if fallback {
+            transcriber.transcribe_fallback(&wav_path)
+        } else {
+            transcriber.transcribe(&wav_path)
+        }
+    })
+    .await
+    .context("transcription task failed")?
+}
+
+/// Wait for the overlay thread to exit and forget its sender.
+fn close_overlay(handle: overlay::OverlayHandle, live_overlay: &LiveOverlay) {
+    handle.join();
+    *live_overlay.lock().unwrap() = None;
+}
+
+/// On Ctrl-C, close any overlay straight away (the main loop may be busy
+/// waiting on the server) and ask the main loop to exit so destructors run.
+/// `shutdown` is set first so a dictation that is still finishing knows not
+/// to paste. A second Ctrl-C exits immediately in case that cleanup hangs.
+fn spawn_signal_handler(
+    tx: mpsc::UnboundedSender<KeyEvent>,
+    live_overlay: LiveOverlay,
+    shutdown: Arc<AtomicBool>,
+) {
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        info!("shutting down (Ctrl-C again to force)");
+        shutdown.store(true, Ordering::Relaxed);
+        if let Some(overlay) = live_overlay.lock().unwrap().as_ref() {
+            let _ = overlay.send(OverlayCommand::Close);
+        }
+        let _ = tx.send(KeyEvent::Shutdown);
+
+        if tokio::signal::ctrl_c().await.is_ok() {
+            warn!("forced exit");
+            std::process::exit(130);
+        }
+    });
+}
+
+/// `justspeak transcribe`: run a recorded file through the server and the
+/// usual text post-processing, and print the result.
+fn transcribe_file(
+    file: &Path,
+    timestamps: Option<TimestampFormat>,
+    server: Option<String>,
+) -> Result<()> {
+    let bytes = if file == Path::new("-") {
+        let mut bytes = Vec::new();
+        std::io::stdin().read_to_end(&mut bytes).context("failed to read stdin")?;
+        bytes
+    } else {
+        std::fs::read(file).with_context(|| format!("failed to read {}", file.display()))?
+    };
+    let samples = audio::decode_audio(&bytes)?;
+
+    let config = config::Config::load();
+    let transcriber = transcribe::Transcriber::new(&config, server);
+    let tmp = tempfile::Builder::new().suffix(".wav").tempfile()?;
+    audio::AudioCapture::write_wav(&samples, tmp.path(), transcriber.audio_format())?;
+
+    let postprocessor = text::PostProcessor::new(&config);
+    let Some(format) = timestamps else {
+        let text = transcriber.transcribe(tmp.path())?;
+        println!("{}", postprocessor.apply(&text, &samples));
+        return Ok(());
+    };
+
+    // Segments carry their own timing, so skip pause-based paragraph breaks
+    let mut segments = transcriber.transcribe_detailed(tmp.path())?;
+    for segment in &mut segments {
+        segment.text = postprocessor.apply(&segment.text, &[]);
+    }
+    match format {
+        TimestampFormat::Json => println!("{}", serde_json::to_string_pretty(&segments)?),
+        TimestampFormat::Srt => print!("{}", format_srt(&segments)),
+    }
+    Ok(())
+}
+
+/// Render segments as SubRip subtitles.
+fn format_srt(segments: &[transcribe::Segment]) -> String {
+    let mut out = String::new();
+    for (i, segment) in segments.iter().enumerate() {
+        out.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            srt_timestamp(segment.start),
+            srt_timestamp(segment.end),
+            segment.text
+        ));
+    }
+    out
+}
+
+/// `HH:MM:SS,mmm`, as SRT wants it.
+fn srt_timestamp(secs: f64) -> String {
+    let ms = (secs.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// Final result of a streamed transcription.
+#[derive(Debug, Default)]
+struct StreamedText {
+    text: String,
+    /// Per-word confidence (0.0-1.0), if the server sent it.
+    confidence: Option<Vec<f32>>,
+}
+
+/// Per-word confidence from a `partial`/`final` message's optional
+/// `"confidence": [0.98, 0.41, ...]` array, one entry per whitespace-separated
+/// word of its text. None if missing, malformed or the wrong length.
+fn word_confidence(data: &serde_json::Value, text: &str) -> Option<Vec<f32>> {
+    let confidence: Vec<f32> = data["confidence"]
+        .as_array()?
+        .iter()
+        .map(|c| c.as_f64().map(|c| c as f32))
+        .collect::<Option<_>>()?;
+    (confidence.len() == text.split_whitespace().count()).then_some(confidence)
+}
+
 /// Stream audio to the nemospeech server over WebSocket, receiving partial
-/// transcription results in real time. Returns the final transcription text.
+/// transcription results in real time. Returns the final transcription.
+///
+/// If the connection drops before `stop` is set, it is re-established (up to
+/// `MAX_STREAM_RECONNECTS` times) and all audio so far is re-sent, so the
+/// server rebuilds the transcript from the start. Each connection attempt
+/// falls over to the next configured server if the current one is down.
+/// Connection state is reported to the overlay as it changes.
 async fn streaming_transcription(
     stop: Arc<AtomicBool>,
     audio_handle: audio::AudioBufferHandle,
-    ws_url: String,
+    transcriber: Arc<transcribe::Transcriber>,
     overlay_tx: std::sync::mpsc::Sender<OverlayCommand>,
-) -> Result<String> {
-    let (ws_stream, _) =
-        tokio_tungstenite::connect_async(&ws_url)
-            .await
-            .context("failed to connect to nemospeech WebSocket")?;
+    partials: Option<paste::PartialSender>,
+    first_partial: Arc<OnceLock<Instant>>,
+) -> Result<StreamedText> {
+    let options = transcriber.stream_options();
+    let status = |link| {
+        let _ = overlay_tx.send(OverlayCommand::Status(link));
+    };
+    status(LinkStatus::Connecting);
+    let mut ws_stream = match transcriber.connect_stream().await {
+        Ok(stream) => stream,
+        Err(e) => {
+            status(LinkStatus::Buffering);
+            return Err(e);
+        }
+    };
+    status(LinkStatus::Streaming);
+    let mut reconnects = 0;
 
-    info!(url = %ws_url, "WebSocket connected for streaming transcription");
+    loop {
+        let err = match stream_session(
+            ws_stream,
+            &stop,
+            &audio_handle,
+            &options,
+            &overlay_tx,
+            &partials,
+            &first_partial,
+        )
+        .await
+        {
+            Ok(text) => return Ok(text),
+            Err(e) => e,
+        };
 
+        ws_stream = loop {
+            if stop.load(Ordering::Relaxed) || reconnects >= MAX_STREAM_RECONNECTS {
+                status(LinkStatus::Buffering);
+                return Err(err);
+            }
+            status(LinkStatus::Connecting);
+            reconnects += 1;
+            warn!(
+                error = %err,
+                attempt = reconnects,
+                max_attempts = MAX_STREAM_RECONNECTS,
+                "streaming connection dropped, reconnecting"
+            );
+            tokio::time::sleep(STREAM_RECONNECT_DELAY).await;
+            match transcriber.connect_stream().await {
+                Ok(stream) => {
+                    status(LinkStatus::Streaming);
+                    break stream;
+                }
+                Err(e) => warn!(error = %e, "WebSocket reconnect failed"),
+            }
+        };
+    }
+}
+
+/// One WebSocket session: send all audio recorded so far and then new audio
+/// as it arrives, until `stop` is set. Errors if the connection fails before
+/// the final result.
+async fn stream_session(
+    ws_stream: transcribe::WsStream,
+    stop: &AtomicBool,
+    audio_handle: &audio::AudioBufferHandle,
+    options: &transcribe::StreamOptions,
+    overlay_tx: &std::sync::mpsc::Sender<OverlayCommand>,
+    partials: &Option<paste::PartialSender>,
+    first_partial: &Arc<OnceLock<Instant>>,
+) -> Result<StreamedText> {
     let (mut write, mut read) = ws_stream.split();
 
-    // Spawn receiver task — forwards partial results to overlay, captures final text
-    let overlay_tx_clone = overlay_tx;
-    let recv_task = tokio::spawn(async move {
-        let mut final_text = String::new();
+    if let Some(handshake) = &options.handshake {
+        write
+            .send(Message::Text(handshake.as_str().into()))
+            .await
+            .context("failed to send stream handshake")?;
+    }
+    let audio_format = options.audio_format;
+
+    // Spawn receiver task — forwards partial results to overlay, captures final text.
+    // Aborted along with the session so an aborted recording leaves nothing behind.
+    let overlay_tx_clone = overlay_tx.clone();
+    let partials = partials.clone();
+    let first_partial = first_partial.clone();
+    let mut recv_task = tokio::spawn(async move {
         while let Some(msg) = read.next().await {
-            let msg = match msg {
-                Ok(m) => m,
-                Err(e) => {
-                    warn!(error = %e, "WebSocket read error");
-                    break;
-                }
-            };
+            let msg = msg.context("WebSocket read error")?;
             if let Message::Text(text) = msg {
                 let data: serde_json::Value = match serde_json::from_str(&text) {
                     Ok(v) => v,
@@ -274,27 +781,33 @@ async fn streaming_transcription(
                 match data["type"].as_str() {
                     Some("partial") => {
                         if let Some(t) = data["text"].as_str() {
+                            first_partial.get_or_init(Instant::now);
                             info!(text = %t, "streaming partial");
-                            let _ = overlay_tx_clone
-                                .send(OverlayCommand::UpdateText(t.to_string()));
+                            let _ = overlay_tx_clone.send(OverlayCommand::UpdateText(
+                                t.to_string(),
+                                word_confidence(&data, t),
+                            ));
+                            if let Some(partials) = &partials {
+                                partials.send(t);
+                            }
                         }
                     }
                     Some("final") => {
-                        if let Some(t) = data["text"].as_str() {
-                            final_text = t.to_string();
-                        }
-                        break;
+                        let text = data["text"].as_str().unwrap_or_default();
+                        let confidence = word_confidence(&data, text);
+                        return Ok(StreamedText { text: text.to_string(), confidence });
                     }
                     _ => {}
                 }
             }
         }
-        final_text
+        anyhow::bail!("WebSocket closed before final transcription")
     });
+    let _abort_recv = AbortOnDrop(recv_task.abort_handle());
 
     // Send audio chunks — only new samples since last send
     let mut last_sent = 0;
-    let mut interval = tokio::time::interval(std::time::Duration::from_millis(100));
+    let mut interval = tokio::time::interval(options.interval);
 
     loop {
         interval.tick().await;
@@ -303,7 +816,7 @@ async fn streaming_transcription(
             // Send any remaining audio before signalling done
             let samples = audio_handle.snapshot();
             if samples.len() > last_sent {
-                let bytes = samples_to_s16le(&samples[last_sent..]);
+                let bytes = encode_samples(&samples[last_sent..], audio_format);
                 let _ = write.send(Message::Binary(bytes.into())).await;
             }
             // Signal end of audio
@@ -313,36 +826,182 @@ async fn streaming_transcription(
             break;
         }
 
+        // The receiver only finishes early if the connection dropped
+        if recv_task.is_finished() {
+            return match (&mut recv_task).await {
+                Ok(Ok(_)) => Err(anyhow::anyhow!("server ended the stream early")),
+                Ok(Err(e)) => Err(e),
+                Err(e) => Err(e).context("recv task failed"),
+            };
+        }
+
         let samples = audio_handle.snapshot();
-        if samples.len() > last_sent {
-            let bytes = samples_to_s16le(&samples[last_sent..]);
-            if write.send(Message::Binary(bytes.into())).await.is_err() {
-                warn!("WebSocket send failed");
-                break;
-            }
+        let pending = samples.len().saturating_sub(last_sent) * audio_format.sample_bytes();
+        if pending > 0 && pending >= options.min_bytes {
+            let bytes = encode_samples(&samples[last_sent..], audio_format);
+            write
+                .send(Message::Binary(bytes.into()))
+                .await
+                .context("WebSocket send failed")?;
             last_sent = samples.len();
         }
     }
 
     // Wait for final transcription from server
-    let final_text = match tokio::time::timeout(
+    let streamed = match tokio::time::timeout(
         std::time::Duration::from_secs(10),
         recv_task,
     )
     .await
     {
-        Ok(Ok(text)) => text,
+        Ok(Ok(result)) => result?,
         Ok(Err(e)) => {
             warn!(error = %e, "recv task failed");
-            String::new()
+            StreamedText::default()
         }
         Err(_) => {
             warn!("timed out waiting for final transcription");
-            String::new()
+            StreamedText::default()
         }
     };
 
-    Ok(final_text)
+    Ok(streamed)
+}
+
+/// Aborts a spawned task when dropped, so cancelling the future that owns it
+/// doesn't leave the task running.
+struct AbortOnDrop(tokio::task::AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Paste settings from the current config, with `--dry-run` applied.
+fn paste_options(config: &config::Config, args: &Args) -> paste::PasteOptions {
+    let mut options = paste::PasteOptions::from_config(config);
+    options.dry_run |= args.dry_run;
+    options
+}
+
+/// Whether a recording is below `[input] min_duration_secs` and should be
+/// dropped. Logs when it is.
+fn too_short(duration: f32, config: &RwLock<config::Config>) -> bool {
+    let min_secs = config.read().unwrap().input.min_duration_secs;
+    let short = duration < min_secs;
+    if short {
+        warn!(duration, min_secs, "recording too short, ignoring");
+    }
+    short
+}
+
+/// Trigger events with contact bounce filtered out. An AltGr press or release
+/// only counts once it has held for the debounce window: if the opposite edge
+/// arrives first, both are dropped. So a press-release blip never starts a
+/// recording, and a release-press blip never ends one.
+struct Debounced {
+    rx: mpsc::UnboundedReceiver<KeyEvent>,
+    window: std::time::Duration,
+    /// An unrelated event that arrived while an edge was settling.
+    held: Option<KeyEvent>,
+}
+
+impl Debounced {
+    fn new(rx: mpsc::UnboundedReceiver<KeyEvent>, config: &config::InputConfig) -> Self {
+        let mut debounced = Self {
+            rx,
+            window: std::time::Duration::ZERO,
+            held: None,
+        };
+        debounced.set_window(config);
+        debounced
+    }
+
+    fn set_window(&mut self, config: &config::InputConfig) {
+        self.window = std::time::Duration::from_millis(config.debounce_ms);
+    }
+
+    async fn recv(&mut self) -> Option<KeyEvent> {
+        if let Some(event) = self.held.take() {
+            return Some(event);
+        }
+        loop {
+            let event = self.rx.recv().await?;
+            let reversal = match event {
+                KeyEvent::AltGrPressed => KeyEvent::AltGrReleased,
+                KeyEvent::AltGrReleased => KeyEvent::AltGrPressed,
+                _ => return Some(event),
+            };
+            if self.window.is_zero() {
+                return Some(event);
+            }
+            match tokio::time::timeout(self.window, self.rx.recv()).await {
+                Ok(Some(next)) if next == reversal => {
+                    debug!(?event, "ignoring trigger bounce");
+                }
+                Ok(Some(next)) => {
+                    self.held = Some(next);
+                    return Some(event);
+                }
+                // Held for the whole window (or the channel closed)
+                Ok(None) | Err(_) => return Some(event),
+            }
+        }
+    }
+}
+
+/// Stage timestamps for one dictation, logged as a single event once the text
+/// has been pasted.
+struct Timings {
+    start: Instant,
+    released: Option<Instant>,
+    /// Set by the streaming receiver when the first partial arrives.
+    first_partial: Arc<OnceLock<Instant>>,
+    final_text: Option<Instant>,
+}
+
+impl Timings {
+    fn start() -> Self {
+        Self {
+            start: Instant::now(),
+            released: None,
+            first_partial: Arc::new(OnceLock::new()),
+            final_text: None,
+        }
+    }
+
+    /// Log each stage's duration in milliseconds: capture (press to release),
+    /// first partial (from press), final (from release) and paste.
+    fn log(&self, audio_secs: f32) {
+        let pasted = Instant::now();
+        let ms = |from: Instant, to: Instant| to.saturating_duration_since(from).as_millis() as u64;
+        debug!(
+            audio_secs,
+            capture_ms = self.released.map(|t| ms(self.start, t)),
+            first_partial_ms = self.first_partial.get().map(|&t| ms(self.start, t)),
+            final_ms = self.released.zip(self.final_text).map(|(r, f)| ms(r, f)),
+            paste_ms = self.final_text.map(|t| ms(t, pasted)),
+            total_ms = ms(self.start, pasted),
+            "dictation timings"
+        );
+    }
+}
+
+/// Remove whatever live typing put in the focused window for a dictation
+/// that ended up producing no text.
+fn erase_live_typing(typer: Option<paste::LiveTyper>) {
+    if let Some(Err(e)) = typer.map(|t| t.finish("")) {
+        warn!(error = %e, "failed to erase live-typed text");
+    }
+}
+
+/// Encode f32 samples in the configured wire format for WebSocket transmission.
+fn encode_samples(samples: &[f32], format: config::AudioFormat) -> Vec<u8> {
+    match format {
+        config::AudioFormat::S16le => samples_to_s16le(samples),
+        config::AudioFormat::F32le => samples_to_f32le(samples),
+    }
 }
 
 /// Convert f32 samples to s16le byte buffer for WebSocket transmission.
@@ -355,28 +1014,12 @@ fn samples_to_s16le(samples: &[f32]) -> Vec<u8> {
     bytes
 }
 
-/// Get cursor position from Hyprland via hyprctl.
-/// Falls back to screen center if unavailable.
-fn get_cursor_position() -> (f32, f32) {
-    if let Ok(output) = std::process::Command::new("hyprctl")
-        .args(["cursorpos", "-j"])
-        .output()
-    {
-        if let Ok(text) = String::from_utf8(output.stdout) {
-            let x = extract_json_number(&text, "x");
-            let y = extract_json_number(&text, "y");
-            if let (Some(x), Some(y)) = (x, y) {
-                return (x, y);
-            }
-        }
+/// Convert f32 samples to raw little-endian f32 bytes, without quantizing.
+fn samples_to_f32le(samples: &[f32]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(samples.len() * 4);
+    for &s in samples {
+        bytes.extend_from_slice(&s.to_le_bytes());
     }
-    (960.0, 800.0)
+    bytes
 }
 
-fn extract_json_number(json: &str, key: &str) -> Option<f32> {
-    let pattern = format!("\"{}\":", key);
-    let start = json.find(&pattern)? + pattern.len();
-    let rest = json[start..].trim_start();
-    let end = rest.find(|c: char| !c.is_ascii_digit() && c != '.' && c != '-')?;
-    rest[..end].parse().ok()
-}
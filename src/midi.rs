@@ -1,69 +1,192 @@
+use crate::config::{MidiConfig, MidiTrigger};
 use crate::input::KeyEvent;
-use midir::{Ignore, MidiInput};
+use midir::{Ignore, MidiInput, MidiInputConnection, MidiInputPort};
+use std::time::Duration;
 use tokio::sync::mpsc;
 use tracing::{debug, info, warn};
 
-const TARGET_CONTROLLER: u8 = 85;
-const PORT_NAME_MATCH: &str = "FS-1-WL";
+/// How often MIDI ports are re-scanned so a pedal plugged in (or unplugged)
+/// after launch is picked up.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(2);
 
 /// Spawn a thread that listens for MIDI foot pedal events.
 /// Sends the same KeyEvent types as the keyboard listener.
-/// If no MIDI device is found, logs a message and returns without error.
-pub fn spawn_listener(tx: mpsc::UnboundedSender<KeyEvent>) {
+/// If no MIDI device is found, keeps re-scanning in the background.
+pub fn spawn_listener(tx: mpsc::UnboundedSender<KeyEvent>, config: MidiConfig) {
     std::thread::spawn(move || {
-        if let Err(e) = midi_listen(tx) {
+        if let Err(e) = midi_listen(tx, config) {
             warn!(error = %e, "MIDI listener error");
         }
     });
 }
 
-fn midi_listen(tx: mpsc::UnboundedSender<KeyEvent>) -> Result<(), Box<dyn std::error::Error>> {
-    let mut midi_in = MidiInput::new("justspeak_midi")?;
-    midi_in.ignore(Ignore::None);
+fn midi_listen(
+    tx: mpsc::UnboundedSender<KeyEvent>,
+    config: MidiConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Enumeration uses its own client, since connecting consumes a MidiInput
+    let scanner = MidiInput::new("justspeak_midi_scan")?;
+    // At most one live connection, keyed by port name
+    let mut connection: Option<(String, MidiInputConnection<()>)> = None;
+    let mut reported_missing = false;
 
-    let in_ports = midi_in.ports();
-    let mut selected_port = None;
+    loop {
+        let wanted = select_port(&scanner, &config);
+
+        // Drop the connection if its port vanished or is no longer the selection
+        let stale =
+            connection.take_if(|(name, _)| wanted.as_ref().map(|(n, _)| n) != Some(&*name));
+        if let Some((name, conn)) = stale {
+            conn.close();
+            info!(name = %name, "MIDI foot pedal disconnected");
+        }
 
-    for port in &in_ports {
-        let name = midi_in.port_name(port)?;
-        if name.contains(PORT_NAME_MATCH) {
-            selected_port = Some(port.clone());
-            info!(name = %name, "MIDI foot pedal connected");
-            break;
+        if connection.is_none() {
+            match wanted {
+                Some((name, port)) => match connect(&port, tx.clone(), config.clone()) {
+                    Ok(conn) => {
+                        info!(name = %name, "MIDI foot pedal connected");
+                        connection = Some((name, conn));
+                        reported_missing = false;
+                    }
+                    Err(e) => warn!(name = %name, error = %e, "failed to connect MIDI port"),
+                },
+                None if !reported_missing => {
+                    info!(
+                        port_match = %config.port_match,
+                        "no MIDI foot pedal found - keyboard-only until one is plugged in"
+                    );
+                    reported_missing = true;
+                }
+                None => {}
+            }
         }
+
+        std::thread::sleep(RESCAN_INTERVAL);
+    }
+}
+
+/// Find the configured port: by exact name if set, otherwise the
+/// `port_index`-th port whose name contains `port_match`.
+fn select_port(midi_in: &MidiInput, config: &MidiConfig) -> Option<(String, MidiInputPort)> {
+    let ports = midi_in
+        .ports()
+        .into_iter()
+        .filter_map(|p| Some((midi_in.port_name(&p).ok()?, p)));
+
+    match &config.port_name {
+        Some(exact) => ports.into_iter().find(|(name, _)| name == exact),
+        None => ports
+            .filter(|(name, _)| name.contains(&config.port_match))
+            .nth(config.port_index),
     }
+}
 
-    let Some(port) = selected_port else {
-        info!("no MIDI foot pedal ({PORT_NAME_MATCH}) found - keyboard-only mode");
-        return Ok(());
-    };
+fn connect(
+    port: &MidiInputPort,
+    tx: mpsc::UnboundedSender<KeyEvent>,
+    config: MidiConfig,
+) -> Result<MidiInputConnection<()>, Box<dyn std::error::Error>> {
+    let mut midi_in = MidiInput::new("justspeak_midi")?;
+    midi_in.ignore(Ignore::None);
 
-    // Keep connection alive by storing it
-    let _conn = midi_in.connect(
-        &port,
+    let conn = midi_in.connect(
+        port,
         "justspeak_midi_read",
         move |_stamp, message, _| {
-            // Check for Control Change message (0xB0-0xBF)
-            if message.len() >= 3 && (message[0] & 0xF0) == 0xB0 {
-                let controller = message[1];
-                let value = message[2];
-
-                if controller == TARGET_CONTROLLER {
-                    if value == 127 {
-                        debug!("MIDI foot pedal pressed");
-                        let _ = tx.send(KeyEvent::AltGrPressed);
-                    } else if value == 0 {
-                        debug!("MIDI foot pedal released");
-                        let _ = tx.send(KeyEvent::AltGrReleased);
-                    }
-                }
+            if let Some(event) = parse_message(&config, message) {
+                debug!(?event, "MIDI foot pedal");
+                let _ = tx.send(event);
             }
         },
         (),
     )?;
+    Ok(conn)
+}
 
-    // Keep thread alive to maintain MIDI connection
-    loop {
-        std::thread::sleep(std::time::Duration::from_secs(1));
+/// Translate a raw MIDI message into a pedal event according to the
+/// configured trigger type.
+fn parse_message(config: &MidiConfig, message: &[u8]) -> Option<KeyEvent> {
+    if message.len() < 3 {
+        return None;
+    }
+    let status = message[0] & 0xF0;
+    let (number, value) = (message[1], message[2]);
+
+    match (config.trigger, status) {
+        // Control Change (0xB0-0xBF)
+        (MidiTrigger::Cc, 0xB0) => {
+            debug!(controller = number, value, "MIDI control change");
+            if config.undo_controller == Some(number) && value == config.press_value {
+                Some(KeyEvent::Undo)
+            } else if config.pause_controller == Some(number) && value == config.press_value {
+                Some(KeyEvent::PauseToggle)
+            } else if number != config.controller {
+                None
+            } else if value == config.press_value {
+                Some(KeyEvent::AltGrPressed)
+            } else if value == config.release_value {
+                Some(KeyEvent::AltGrReleased)
+            } else {
+                None
+            }
+        }
+        // Note On (0x90); velocity 0 is a release by convention
+        (MidiTrigger::Note, 0x90) => {
+            debug!(note = number, velocity = value, "MIDI note on");
+            (number == config.note).then_some(if value > 0 {
+                KeyEvent::AltGrPressed
+            } else {
+                KeyEvent::AltGrReleased
+            })
+        }
+        // Note Off (0x80)
+        (MidiTrigger::Note, 0x80) => {
+            debug!(note = number, "MIDI note off");
+            (number == config.note).then_some(KeyEvent::AltGrReleased)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note_config() -> MidiConfig {
+        MidiConfig {
+            trigger: MidiTrigger::Note,
+            ..MidiConfig::default()
+        }
+    }
+
+    #[test]
+    fn cc_is_the_default_trigger() {
+        let config = MidiConfig::default();
+        let (cc, press, release) = (config.controller, config.press_value, config.release_value);
+        assert_eq!(parse_message(&config, &[0xB0, cc, press]), Some(KeyEvent::AltGrPressed));
+        assert_eq!(parse_message(&config, &[0xB3, cc, release]), Some(KeyEvent::AltGrReleased));
+        assert_eq!(parse_message(&config, &[0x90, config.note, 100]), None);
+    }
+
+    #[test]
+    fn note_on_presses() {
+        let config = note_config();
+        let n = config.note;
+        assert_eq!(parse_message(&config, &[0x90, n, 100]), Some(KeyEvent::AltGrPressed));
+        assert_eq!(parse_message(&config, &[0x90, n.wrapping_add(1), 100]), None);
+    }
+
+    #[test]
+    fn note_on_velocity_zero_releases() {
+        let config = note_config();
+        assert_eq!(parse_message(&config, &[0x90, config.note, 0]), Some(KeyEvent::AltGrReleased));
+    }
+
+    #[test]
+    fn note_off_releases() {
+        let config = note_config();
+        assert_eq!(parse_message(&config, &[0x80, config.note, 64]), Some(KeyEvent::AltGrReleased));
+        assert_eq!(parse_message(&config, &[0xB0, config.controller, 127]), None);
     }
 }
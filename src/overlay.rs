@@ -1,12 +1,15 @@
+use crate::config::{OverlayConfig, OverlayLayer};
+use crate::hyprctl;
 use anyhow::{Context, Result};
 use cosmic_text::{
-    Attrs, Buffer as TextBuffer, Color as CColor, FontSystem, Metrics, Shaping, SwashCache,
+    Attrs, Buffer as TextBuffer, Color as CColor, FontSystem, LayoutRun, Metrics, Shaping,
+    SwashCache,
 };
 use smithay_client_toolkit::{
     compositor::{CompositorHandler, CompositorState, Region},
     delegate_compositor, delegate_layer, delegate_output, delegate_pointer, delegate_registry,
     delegate_seat, delegate_shm,
-    output::{OutputHandler, OutputState},
+    output::{OutputHandler, OutputInfo, OutputState},
     registry::{ProvidesRegistryState, RegistryState},
     registry_handlers,
     seat::{
@@ -20,16 +23,19 @@ use smithay_client_toolkit::{
         },
         WaylandSurface,
     },
-    shm::{slot::SlotPool, Shm, ShmHandler},
+    shm::{
+        slot::{Buffer, CreateBufferError, SlotPool},
+        Shm, ShmHandler,
+    },
 };
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{mpsc, Arc};
-use std::time::Instant;
-use tracing::{info, warn};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
 use wayland_client::{
-    globals::registry_queue_init,
+    globals::{registry_queue_init, GlobalList},
     protocol::{wl_output, wl_pointer, wl_seat, wl_shm, wl_surface},
-    Connection, QueueHandle,
+    Connection, EventQueue, QueueHandle,
 };
 
 // ---- Constants ----
@@ -41,6 +47,14 @@ const DISPLAY_LINE_HEIGHT: f32 = 72.0;
 const END_LINE_HEIGHT: f32 = 18.0;
 const RECORDING_DOT_RADIUS: f32 = 8.0;
 const RECORDING_DOT_MARGIN: f32 = 24.0;
+// Elapsed-time readout
+const TIMER_FONT_SIZE: f32 = 14.0;
+const TIMER_LINE_HEIGHT: f32 = 18.0;
+const TIMER_GAP: f32 = 10.0;
+const TIMER_ALPHA: u8 = 0xB0;
+/// The recording panel never grows past this fraction of the output height;
+/// older lines scroll off the top instead.
+const PANEL_MAX_HEIGHT_FRAC: f32 = 0.6;
 
 // Panel styling
 const PANEL_PADDING: f32 = 24.0;
@@ -69,10 +83,22 @@ const BEZIER_ARC: f32 = 0.25;
 // Cursor polling
 const CURSOR_POLL_MS: u128 = 50;
 
+// Panel following the cursor (`follow_cursor`)
+/// Time constant of the easing; the panel covers ~63% of the way to the
+/// cursor in this long, so 50ms cursor polls don't show as jumps.
+const FOLLOW_SMOOTHING_SECS: f32 = 0.2;
+/// Gap between the cursor and the nearest panel edge, i.e. the tail length.
+const FOLLOW_CURSOR_GAP: f32 = TAIL_MIN_LENGTH + 16.0;
+/// Closest the panel gets to the output edges.
+const FOLLOW_SCREEN_MARGIN: f32 = 16.0;
+
 // Per-character grow animation
 const CHAR_GROW_DURATION: f32 = 0.25;
 const CHAR_STAGGER: f32 = 0.025;
 
+/// Opacity of words below `confidence_threshold`, relative to the rest.
+const LOW_CONFIDENCE_ALPHA: f32 = 0.45;
+
 // Cancel button (inside panel, bottom-right)
 const CANCEL_BTN_WIDTH: u32 = 80;
 const CANCEL_BTN_HEIGHT: u32 = 28;
@@ -91,12 +117,31 @@ const CANCEL_BTN_LINE_HEIGHT: f32 = 18.0;
 // ---- Public API ----
 
 /// Commands sent to the overlay thread.
+/// Text commands carry optional per-word confidence (0.0-1.0), one entry per
+/// whitespace-separated word; without it every word is drawn at full strength.
 pub enum OverlayCommand {
-    UpdateText(String),
-    Finish(String, f32, f32),
+    UpdateText(String, Option<Vec<f32>>),
+    /// Recording paused (true) or resumed (false).
+    Paused(bool),
+    /// State of the streaming connection, shown by the recording dot.
+    Status(LinkStatus),
+    /// Final text, its confidence and the global cursor position to fly to.
+    Finish(String, Option<Vec<f32>>, f32, f32),
     Close,
 }
 
+/// Streaming connection state for the current recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkStatus {
+    /// Connecting or reconnecting to the streaming server.
+    Connecting,
+    /// Connected; audio is streamed and partials come back.
+    Streaming,
+    /// No stream: audio is only buffered for an HTTP transcription on
+    /// release. Non-streaming backends stay here.
+    Buffering,
+}
+
 /// Handle to a running overlay thread.
 pub struct OverlayHandle {
     pub tx: mpsc::Sender<OverlayCommand>,
@@ -116,12 +161,17 @@ impl OverlayHandle {
     }
 }
 
-pub fn spawn_overlay() -> Result<OverlayHandle> {
+/// Start the overlay on its own thread. The Wayland connection and required
+/// globals are set up before the thread starts, so a session without Wayland
+/// or layer-shell fails here rather than leaving a dead overlay behind.
+pub fn spawn_overlay(config: &OverlayConfig) -> Result<OverlayHandle> {
+    let wayland = WaylandGlobals::bind()?;
     let (tx, rx) = mpsc::channel();
     let cancelled = Arc::new(AtomicBool::new(false));
     let cancelled_clone = cancelled.clone();
+    let config = config.clone();
     let join = std::thread::spawn(move || {
-        if let Err(e) = run_overlay_thread(rx, cancelled_clone) {
+        if let Err(e) = run_overlay_thread(wayland, rx, cancelled_clone, config) {
             warn!(error = %e, "overlay thread failed");
         }
     });
@@ -130,12 +180,52 @@ pub fn spawn_overlay() -> Result<OverlayHandle> {
 
 // ---- Private types ----
 
+/// A Wayland connection with the globals the overlay can't work without.
+struct WaylandGlobals {
+    _conn: Connection,
+    globals: GlobalList,
+    event_queue: EventQueue<OverlayState>,
+    compositor: CompositorState,
+    layer_shell: LayerShell,
+    shm: Shm,
+}
+
+impl WaylandGlobals {
+    fn bind() -> Result<Self> {
+        let conn = Connection::connect_to_env().context("failed to connect to Wayland")?;
+        let (globals, event_queue) = registry_queue_init(&conn)?;
+        let qh = event_queue.handle();
+
+        let compositor =
+            CompositorState::bind(&globals, &qh).context("wl_compositor not available")?;
+        let layer_shell =
+            LayerShell::bind(&globals, &qh).context("wlr-layer-shell not available")?;
+        let shm = Shm::bind(&globals, &qh).context("wl_shm not available")?;
+
+        Ok(Self {
+            _conn: conn,
+            globals,
+            event_queue,
+            compositor,
+            layer_shell,
+            shm,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Phase {
     Recording,
     FlyOut,
 }
 
+/// What the recording dot shows.
+#[derive(Debug, Clone, Copy)]
+enum DotState {
+    Paused,
+    Recording(LinkStatus),
+}
+
 struct OverlayState {
     registry_state: RegistryState,
     output_state: OutputState,
@@ -143,22 +233,40 @@ struct OverlayState {
     seat_state: SeatState,
     shm: Shm,
     pool: SlotPool,
-    layer: LayerSurface,
+    /// Created after the initial roundtrip, once the target output is known.
+    layer: Option<LayerSurface>,
     font_system: FontSystem,
     swash_cache: SwashCache,
     rx: mpsc::Receiver<OverlayCommand>,
     text: String,
+    /// Cursor position in output-local coordinates.
     cursor_x: f32,
     cursor_y: f32,
+    /// Global logical position of the output the overlay is bound to.
+    output_origin: (f32, f32),
     width: u32,
     height: u32,
     first_configure: bool,
+    /// A frame has been committed, so frame callbacks will keep coming.
+    frame_shown: bool,
+    /// The last frame couldn't get a buffer; logged once per run of failures.
+    frame_failed: bool,
     phase: Phase,
     fly_start: Instant,
     recording_start: Instant,
+    /// Set while the recording is paused.
+    paused_since: Option<Instant>,
+    /// Time spent paused before `paused_since`, left out of the timer.
+    paused_total: Duration,
+    link: LinkStatus,
     last_cursor_poll: Instant,
     /// Per-character animation birth times (indexed by char index).
     char_birth_times: Vec<Instant>,
+    /// Per-word confidence of `text`, empty if the server sent none.
+    word_confidence: Vec<f32>,
+    /// Per-character low-confidence flags (indexed like `char_birth_times`).
+    char_dimmed: Vec<bool>,
+    confidence_threshold: f32,
     done: bool,
     // Pointer / cancel button state
     pointer: Option<wl_pointer::WlPointer>,
@@ -166,40 +274,43 @@ struct OverlayState {
     pointer_pos: (f64, f64),
     pointer_hover: bool,
     cancelled: Arc<AtomicBool>,
+    /// Only the cancel button takes pointer input, not the whole panel.
+    click_through: bool,
+    show_timer: bool,
+    /// Panel position when `follow_cursor` is on, None otherwise.
+    follow: Option<PanelFollow>,
+}
+
+/// Eased panel position for `follow_cursor`.
+struct PanelFollow {
+    /// Panel center in output-local coordinates; None until the first frame.
+    center: Option<(f32, f32)>,
+    last_frame: Instant,
 }
 
 // ---- Overlay thread ----
 
-fn run_overlay_thread(rx: mpsc::Receiver<OverlayCommand>, cancelled: Arc<AtomicBool>) -> Result<()> {
+fn run_overlay_thread(
+    wayland: WaylandGlobals,
+    rx: mpsc::Receiver<OverlayCommand>,
+    cancelled: Arc<AtomicBool>,
+    config: OverlayConfig,
+) -> Result<()> {
     info!("overlay thread starting");
 
-    let conn = Connection::connect_to_env().context("failed to connect to Wayland")?;
-    let (globals, mut event_queue) = registry_queue_init(&conn)?;
+    let WaylandGlobals {
+        _conn,
+        globals,
+        mut event_queue,
+        compositor,
+        layer_shell,
+        shm,
+    } = wayland;
     let qh = event_queue.handle();
-
-    let compositor =
-        CompositorState::bind(&globals, &qh).context("wl_compositor not available")?;
-    let layer_shell =
-        LayerShell::bind(&globals, &qh).context("wlr-layer-shell not available")?;
-    let shm = Shm::bind(&globals, &qh).context("wl_shm not available")?;
     let seat_state = SeatState::new(&globals, &qh);
 
-    let surface = compositor.create_surface(&qh);
-    let layer =
-        layer_shell.create_layer_surface(&qh, surface, Layer::Overlay, Some("justspeak"), None);
-
-    layer.set_anchor(Anchor::TOP | Anchor::BOTTOM | Anchor::LEFT | Anchor::RIGHT);
-    layer.set_exclusive_zone(-1);
-    layer.set_keyboard_interactivity(KeyboardInteractivity::None);
-
-    // Start with empty input region — will be updated per-frame to cover
-    // only the cancel button during Recording phase.
-    let empty_region = Region::new(&compositor).context("failed to create region")?;
-    layer.wl_surface().set_input_region(Some(empty_region.wl_region()));
-
-    layer.commit();
-
     let font_system = FontSystem::new();
+    log_font_coverage(&font_system);
     let swash_cache = SwashCache::new();
     let pool = SlotPool::new(256 * 256 * 4, &shm)?;
 
@@ -213,28 +324,69 @@ fn run_overlay_thread(rx: mpsc::Receiver<OverlayCommand>, cancelled: Arc<AtomicB
         seat_state,
         shm,
         pool,
-        layer,
+        layer: None,
         font_system,
         swash_cache,
         rx,
         text: String::new(),
         cursor_x: cx,
         cursor_y: cy,
+        output_origin: (0.0, 0.0),
         width: 0,
         height: 0,
         first_configure: true,
+        frame_shown: false,
+        frame_failed: false,
         phase: Phase::Recording,
         fly_start: now,
         recording_start: now,
+        paused_since: None,
+        paused_total: Duration::ZERO,
+        link: LinkStatus::Buffering,
         last_cursor_poll: now,
         char_birth_times: Vec::new(),
+        word_confidence: Vec::new(),
+        char_dimmed: Vec::new(),
+        confidence_threshold: config.confidence_threshold,
         done: false,
         pointer: None,
         cancel_btn_rect: None,
         pointer_pos: (0.0, 0.0),
         pointer_hover: false,
         cancelled,
+        click_through: config.click_through,
+        show_timer: config.show_timer,
+        follow: config.follow_cursor.then_some(PanelFollow { center: None, last_frame: now }),
+    };
+
+    // Receive output geometry so we can bind to the monitor under the cursor
+    event_queue.roundtrip(&mut state)?;
+    let output = state.select_output(cx, cy);
+    if let Some(output) = &output {
+        state.reserve_frames(output);
+    }
+
+    let surface = state.compositor.create_surface(&qh);
+    let shell_layer = match config.layer {
+        OverlayLayer::Overlay => Layer::Overlay,
+        OverlayLayer::Top => Layer::Top,
     };
+    let layer = layer_shell.create_layer_surface(
+        &qh, surface, shell_layer, Some("justspeak"), output.as_ref(),
+    );
+
+    layer.set_anchor(Anchor::TOP | Anchor::BOTTOM | Anchor::LEFT | Anchor::RIGHT);
+    layer.set_exclusive_zone(-1);
+    layer.set_keyboard_interactivity(KeyboardInteractivity::None);
+
+    // Start with empty input region — will be updated per-frame to cover
+    // the cancel button (or the panel, without click_through) during
+    // Recording phase.
+    let empty_region = Region::new(&state.compositor).context("failed to create region")?;
+    layer.wl_surface().set_input_region(Some(empty_region.wl_region()));
+
+    layer.commit();
+    state.layer = Some(layer);
 
     while !state.done {
         event_queue.blocking_dispatch(&mut state)?;
@@ -246,33 +398,71 @@ fn run_overlay_thread(rx: mpsc::Receiver<OverlayCommand>, cancelled: Arc<AtomicB
 
 // ---- Primitive drawing helpers ----
 
-fn put_pixel(canvas: &mut [u8], cw: usize, ch: usize, px: usize, py: usize, pixel: u32) {
+fn premul_argb(r: u8, g: u8, b: u8, a: u8) -> u32 {
+    let a32 = a as u32;
+    (a32 << 24) | (r as u32 * a32 / 255) << 16 | (g as u32 * a32 / 255) << 8 | (b as u32 * a32 / 255)
+}
+
+/// Apply `f` to each 8-bit channel of two packed ARGB pixels.
+fn map_channels(a: u32, b: u32, f: impl Fn(u32, u32) -> u32) -> u32 {
+    [0, 8, 16, 24].iter().fold(0, |out, shift| {
+        out | f((a >> shift) & 0xFF, (b >> shift) & 0xFF).min(0xFF) << shift
+    })
+}
+
+/// Scale a premultiplied pixel by `coverage` (0..=1), fading it out.
+fn scale_premul(pixel: u32, coverage: f32) -> u32 {
+    map_channels(pixel, 0, |c, _| (c as f32 * coverage).round() as u32)
+}
+
+/// Linear mix of two premultiplied pixels; `t` = 0 gives `a`, 1 gives `b`.
+fn mix_premul(a: u32, b: u32, t: f32) -> u32 {
+    map_channels(a, b, |ca, cb| (ca as f32 * (1.0 - t) + cb as f32 * t).round() as u32)
+}
+
+/// Composite a premultiplied ARGB pixel over the canvas (source-over), so
+/// overlapping shapes and glyphs show through each other by their alpha
+/// instead of the last one drawn winning.
+fn blend_pixel(canvas: &mut [u8], cw: usize, ch: usize, px: usize, py: usize, pixel: u32) {
     if px < cw && py < ch {
         let idx = (py * cw + px) * 4;
         if idx + 3 < canvas.len() {
-            canvas[idx..idx + 4].copy_from_slice(&pixel.to_le_bytes());
+            let mut dst = [0u8; 4];
+            dst.copy_from_slice(&canvas[idx..idx + 4]);
+            let inv = 255 - (pixel >> 24);
+            let out = map_channels(pixel, u32::from_le_bytes(dst), |s, d| s + (d * inv + 127) / 255);
+            canvas[idx..idx + 4].copy_from_slice(&out.to_le_bytes());
         }
     }
 }
 
-fn premul_argb(r: u8, g: u8, b: u8, a: u8) -> u32 {
-    let a32 = a as u32;
-    (a32 << 24) | (r as u32 * a32 / 255) << 16 | (g as u32 * a32 / 255) << 8 | (b as u32 * a32 / 255)
+/// Blend `color` over `coverage` (0..=1) of a pixel; partly covered edge
+/// pixels fade out for anti-aliasing.
+fn plot(canvas: &mut [u8], cw: usize, ch: usize, px: usize, py: usize, color: u32, coverage: f32) {
+    if coverage >= 1.0 {
+        blend_pixel(canvas, cw, ch, px, py, color);
+    } else if coverage > 0.0 {
+        blend_pixel(canvas, cw, ch, px, py, scale_premul(color, coverage));
+    }
+}
+
+/// Pixel coverage from the signed distance (in pixels, negative inside) of
+/// the pixel center to a shape's edge.
+fn edge_coverage(distance: f32) -> f32 {
+    (0.5 - distance).clamp(0.0, 1.0)
 }
 
 fn draw_circle(canvas: &mut [u8], cw: usize, ch: usize, cx: f32, cy: f32, radius: f32, color: u32) {
-    let r2 = radius * radius;
-    let x0 = (cx - radius).max(0.0) as usize;
-    let x1 = ((cx + radius) as usize + 1).min(cw);
-    let y0 = (cy - radius).max(0.0) as usize;
-    let y1 = ((cy + radius) as usize + 1).min(ch);
+    let x0 = (cx - radius - 1.0).max(0.0) as usize;
+    let x1 = ((cx + radius + 1.0) as usize + 1).min(cw);
+    let y0 = (cy - radius - 1.0).max(0.0) as usize;
+    let y1 = ((cy + radius + 1.0) as usize + 1).min(ch);
     for py in y0..y1 {
         for px in x0..x1 {
-            let dx = px as f32 - cx;
-            let dy = py as f32 - cy;
-            if dx * dx + dy * dy <= r2 {
-                put_pixel(canvas, cw, ch, px, py, color);
-            }
+            let dx = px as f32 + 0.5 - cx;
+            let dy = py as f32 + 0.5 - cy;
+            let coverage = edge_coverage(dx.hypot(dy) - radius);
+            plot(canvas, cw, ch, px, py, color, coverage);
         }
     }
 }
@@ -282,41 +472,43 @@ fn draw_filled_triangle(
     x0: f32, y0: f32, x1: f32, y1: f32, x2: f32, y2: f32,
     color: u32,
 ) {
-    let min_x = x0.min(x1).min(x2).max(0.0) as usize;
-    let max_x = (x0.max(x1).max(x2) as usize + 1).min(cw);
-    let min_y = y0.min(y1).min(y2).max(0.0) as usize;
-    let max_y = (y0.max(y1).max(y2) as usize + 1).min(ch);
+    // Twice the signed area; its sign says which side of each edge is inside
+    let area = (x1 - x0) * (y2 - y0) - (y1 - y0) * (x2 - x0);
+    if area.abs() < f32::EPSILON {
+        return;
+    }
+    let edges = [(x0, y0, x1, y1), (x1, y1, x2, y2), (x2, y2, x0, y0)];
+
+    let min_x = (x0.min(x1).min(x2) - 1.0).max(0.0) as usize;
+    let max_x = ((x0.max(x1).max(x2) + 1.0) as usize + 1).min(cw);
+    let min_y = (y0.min(y1).min(y2) - 1.0).max(0.0) as usize;
+    let max_y = ((y0.max(y1).max(y2) + 1.0) as usize + 1).min(ch);
 
     for py in min_y..max_y {
         for px in min_x..max_x {
             let fpx = px as f32 + 0.5;
             let fpy = py as f32 + 0.5;
-            // Barycentric sign test
-            let d1 = (fpx - x1) * (y0 - y1) - (x0 - x1) * (fpy - y1);
-            let d2 = (fpx - x2) * (y1 - y2) - (x1 - x2) * (fpy - y2);
-            let d3 = (fpx - x0) * (y2 - y0) - (x2 - x0) * (fpy - y0);
-            let has_neg = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
-            let has_pos = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
-            if !(has_neg && has_pos) {
-                put_pixel(canvas, cw, ch, px, py, color);
-            }
+            // Distance to the nearest edge, positive inside
+            let inside = edges
+                .iter()
+                .map(|&(ax, ay, bx, by)| {
+                    let cross = (bx - ax) * (fpy - ay) - (by - ay) * (fpx - ax);
+                    cross * area.signum() / (bx - ax).hypot(by - ay).max(0.001)
+                })
+                .fold(f32::INFINITY, f32::min);
+            plot(canvas, cw, ch, px, py, color, edge_coverage(-inside));
         }
     }
 }
 
-fn corner_center(lx: f32, ly: f32, rw: f32, rh: f32, radius: f32) -> (Option<f32>, Option<f32>) {
-    let in_left = lx < radius;
-    let in_right = lx >= rw - radius;
-    let in_top = ly < radius;
-    let in_bottom = ly >= rh - radius;
-    match (in_left || in_right, in_top || in_bottom) {
-        (true, true) => {
-            let cx = if in_left { radius } else { rw - radius };
-            let cy = if in_top { radius } else { rh - radius };
-            (Some(cx), Some(cy))
-        }
-        _ => (None, None),
-    }
+/// Signed distance from (`x`, `y`) to the edge of a `w`×`h` rectangle at the
+/// origin with corners rounded to `radius`. Negative inside.
+fn rounded_rect_distance(x: f32, y: f32, w: f32, h: f32, radius: f32) -> f32 {
+    let (hw, hh) = (w / 2.0, h / 2.0);
+    let radius = radius.min(hw).min(hh);
+    let qx = (x - hw).abs() - (hw - radius);
+    let qy = (y - hh).abs() - (hh - radius);
+    qx.max(0.0).hypot(qy.max(0.0)) + qx.max(qy).min(0.0) - radius
 }
 
 fn draw_rounded_rect(
@@ -335,22 +527,16 @@ fn draw_rounded_rect(
 
     for py in y0..y1 {
         for px in x0..x1 {
-            let lx = px as f32 - frx;
-            let ly = py as f32 - fry;
-            let (ccx, ccy) = corner_center(lx, ly, fw, fh, radius);
-            let inside = match (ccx, ccy) {
-                (Some(cx), Some(cy)) => {
-                    let dx = lx - cx;
-                    let dy = ly - cy;
-                    let dist = (dx * dx + dy * dy).sqrt();
-                    if dist > radius + 0.5 {
-                        continue;
-                    }
-                    dist <= radius - bw
-                }
-                _ => lx >= bw && lx < fw - bw && ly >= bw && ly < fh - bw,
-            };
-            put_pixel(canvas, cw, ch, px, py, if inside { fill } else { border });
+            let lx = px as f32 + 0.5 - frx;
+            let ly = py as f32 + 0.5 - fry;
+            let dist = rounded_rect_distance(lx, ly, fw, fh, radius);
+            let coverage = edge_coverage(dist);
+            if coverage <= 0.0 {
+                continue;
+            }
+            // The fill's edge is the outline inset by the border width
+            let color = mix_premul(border, fill, edge_coverage(dist + bw));
+            plot(canvas, cw, ch, px, py, color, coverage);
         }
     }
 }
@@ -374,34 +560,70 @@ fn bezier_deriv(t: f32, p0: f32, p1: f32, p2: f32) -> f32 {
 // ---- Cursor position via hyprctl ----
 
 fn read_cursor_position() -> (f32, f32) {
-    if let Ok(output) = std::process::Command::new("hyprctl")
-        .args(["cursorpos", "-j"])
-        .output()
-    {
-        if let Ok(text) = String::from_utf8(output.stdout) {
-            if let (Some(x), Some(y)) = (json_num(&text, "x"), json_num(&text, "y")) {
-                return (x, y);
-            }
-        }
-    }
-    (960.0, 800.0)
-}
-
-fn json_num(json: &str, key: &str) -> Option<f32> {
-    let pat = format!("\"{}\":", key);
-    let start = json.find(&pat)? + pat.len();
-    let rest = json[start..].trim_start();
-    let end = rest.find(|c: char| !c.is_ascii_digit() && c != '.' && c != '-')?;
-    rest[..end].parse().ok()
+    hyprctl::cursor_position().unwrap_or(hyprctl::FALLBACK_CURSOR)
 }
 
 // ---- OverlayState impl ----
 
 impl OverlayState {
+    fn layer(&self) -> &LayerSurface {
+        self.layer.as_ref().expect("layer surface created before first configure")
+    }
+
+    /// Pick the output containing the global cursor position and translate the
+    /// cursor into its local space. Falls back to the first (primary) output
+    /// when the cursor monitor can't be determined.
+    fn select_output(&mut self, cx: f32, cy: f32) -> Option<wl_output::WlOutput> {
+        let outputs: Vec<_> = self.output_state
+            .outputs()
+            .filter_map(|o| {
+                let info = self.output_state.info(&o)?;
+                let pos = info.logical_position.unwrap_or(info.location);
+                let size = output_size(&info)?;
+                Some((o, pos, size))
+            })
+            .collect();
+
+        let under_cursor = outputs.iter().find(|(_, (x, y), (w, h))| {
+            cx >= *x as f32 && cx < (*x + *w) as f32 && cy >= *y as f32 && cy < (*y + *h) as f32
+        });
+
+        let (output, (ox, oy), _) = match under_cursor.or(outputs.first()) {
+            Some(o) => o.clone(),
+            None => {
+                warn!("no output geometry available, letting compositor choose");
+                return None;
+            }
+        };
+        if under_cursor.is_none() {
+            warn!(cx, cy, "cursor not on any known output, using primary output");
+        }
+
+        self.output_origin = (ox as f32, oy as f32);
+        self.cursor_x = cx - self.output_origin.0;
+        self.cursor_y = cy - self.output_origin.1;
+        info!(x = ox, y = oy, "binding overlay to output");
+        Some(output)
+    }
+
+    /// Size the shm pool for the full-output surface up front: room for two
+    /// frames, one on screen and one being drawn, rather than growing it
+    /// step by step from the first frames. If that fails, frames are still
+    /// allocated one at a time.
+    fn reserve_frames(&mut self, output: &wl_output::WlOutput) {
+        let Some((w, h)) = self.output_state.info(output).as_ref().and_then(output_size) else {
+            return;
+        };
+        let bytes = w.max(0) as usize * h.max(0) as usize * 4 * 2;
+        if let Err(e) = self.pool.resize(bytes) {
+            warn!(error = %e, bytes, "failed to reserve overlay buffers");
+        }
+    }
+
     fn poll_commands(&mut self) {
         while let Ok(cmd) = self.rx.try_recv() {
             match cmd {
-                OverlayCommand::UpdateText(text) => {
+                OverlayCommand::UpdateText(text, confidence) => {
                     if self.phase == Phase::Recording && text != self.text {
                         let now = Instant::now();
                         let old_chars: Vec<char> = self.text.chars().collect();
@@ -426,11 +648,27 @@ impl OverlayState {
                         self.char_birth_times = new_times;
                         self.text = text;
                     }
+                    if self.phase == Phase::Recording {
+                        self.set_confidence(confidence);
+                    }
+                }
+                OverlayCommand::Paused(true) => {
+                    self.paused_since.get_or_insert_with(Instant::now);
+                }
+                OverlayCommand::Paused(false) => {
+                    if let Some(since) = self.paused_since.take() {
+                        self.paused_total += since.elapsed();
+                    }
+                }
+                OverlayCommand::Status(link) => {
+                    self.link = link;
                 }
-                OverlayCommand::Finish(text, cx, cy) => {
-                    self.text = text;
-                    self.cursor_x = cx;
-                    self.cursor_y = cy;
+                OverlayCommand::Finish(text, confidence, cx, cy) => {
+                    // Fly out what the panel was showing, not the whole transcript
+                    self.text = self.visible_tail(text);
+                    self.set_confidence(confidence);
+                    self.cursor_x = cx - self.output_origin.0;
+                    self.cursor_y = cy - self.output_origin.1;
                     self.phase = Phase::FlyOut;
                     self.fly_start = Instant::now();
                 }
@@ -441,13 +679,54 @@ impl OverlayState {
         }
     }
 
+    /// Take new per-word confidence for `text` and recompute which characters
+    /// are dimmed.
+    fn set_confidence(&mut self, confidence: Option<Vec<f32>>) {
+        self.word_confidence = confidence.unwrap_or_default();
+        self.char_dimmed =
+            low_confidence_chars(&self.text, &self.word_confidence, self.confidence_threshold);
+    }
+
+    /// The part of `text` the recording panel shows: all of it when it fits,
+    /// otherwise "…" followed by the last lines that fit in the capped panel.
+    fn visible_tail(&mut self, text: String) -> String {
+        let max_tw = (self.width as f32 * 0.8).max(200.0);
+        let (_, th, buf) = Self::layout_text(
+            &mut self.font_system, &text,
+            DISPLAY_FONT_SIZE, DISPLAY_LINE_HEIGHT, max_tw,
+        );
+        let (scroll, _) = tail_scroll(&buf, th, max_text_height(self.height));
+        if scroll <= 0.0 {
+            return text;
+        }
+
+        // Byte offset of each buffer line, since glyph offsets are line-relative
+        let line_byte_starts: Vec<usize> = text
+            .split('\n')
+            .scan(0, |next, line| {
+                let start = *next;
+                *next += line.len() + 1;
+                Some(start)
+            })
+            .collect();
+        let start = buf
+            .layout_runs()
+            .find(|run| run.line_top >= scroll)
+            .and_then(|run| Some(line_byte_starts.get(run.line_i)? + run.glyphs.first()?.start));
+
+        match start.and_then(|i| text.get(i..)) {
+            Some(tail) => format!("…{}", tail.trim_start()),
+            None => text,
+        }
+    }
+
     fn poll_cursor(&mut self) {
         let now = Instant::now();
         if now.duration_since(self.last_cursor_poll).as_millis() >= CURSOR_POLL_MS {
             self.last_cursor_poll = now;
             let (cx, cy) = read_cursor_position();
-            self.cursor_x = cx;
-            self.cursor_y = cy;
+            self.cursor_x = cx - self.output_origin.0;
+            self.cursor_y = cy - self.output_origin.1;
         }
     }
 
@@ -469,12 +748,38 @@ impl OverlayState {
 
     fn layout_text(
         fs: &mut FontSystem, text: &str, font_size: f32, line_height: f32,
-        max_w: f32, max_h: f32,
+        max_w: f32,
+    ) -> (f32, f32, TextBuffer) {
+        Self::layout_dimmed_text(fs, text, &[], font_size, line_height, max_w)
+    }
+
+    /// Like `layout_text`, with the characters flagged in `dimmed` drawn at
+    /// `LOW_CONFIDENCE_ALPHA`.
+    fn layout_dimmed_text(
+        fs: &mut FontSystem, text: &str, dimmed: &[bool], font_size: f32, line_height: f32,
+        max_w: f32,
     ) -> (f32, f32, TextBuffer) {
         let metrics = Metrics::new(font_size, line_height);
         let mut buf = TextBuffer::new(fs, metrics);
-        buf.set_size(fs, Some(max_w), Some(max_h));
-        buf.set_text(fs, text, Attrs::new().family(cosmic_text::Family::SansSerif), Shaping::Advanced);
+        // Unbounded height: callers decide which lines to show
+        buf.set_size(fs, Some(max_w), None);
+        let attrs = Attrs::new().family(cosmic_text::Family::SansSerif);
+        let dim_attrs = attrs.color(CColor::rgba(0xFF, 0xFF, 0xFF, (LOW_CONFIDENCE_ALPHA * 255.0) as u8));
+
+        // Runs of equally dimmed characters become spans
+        let mut spans = Vec::new();
+        let mut span_start = 0;
+        let mut span_dim = false;
+        for (i, (byte, _)) in text.char_indices().enumerate() {
+            let dim = dimmed.get(i).copied().unwrap_or(false);
+            if dim != span_dim {
+                spans.push((&text[span_start..byte], if span_dim { dim_attrs } else { attrs }));
+                span_start = byte;
+                span_dim = dim;
+            }
+        }
+        spans.push((&text[span_start..], if span_dim { dim_attrs } else { attrs }));
+        buf.set_rich_text(fs, spans, attrs, Shaping::Advanced);
         buf.shape_until_scroll(fs, false);
         let mut tw = 0.0_f32;
         let mut th = 0.0_f32;
@@ -489,7 +794,9 @@ impl OverlayState {
         fs: &mut FontSystem, sc: &mut SwashCache, buf: &mut TextBuffer,
         canvas: &mut [u8], cw: usize, ch: usize, ox: i32, oy: i32, alpha: u8,
     ) {
-        let color = CColor::rgba(0xFF, 0xFF, 0xFF, alpha);
+        // Applied here rather than through the draw color, which spans with
+        // their own color (dimmed words) would override
+        let color = CColor::rgba(0xFF, 0xFF, 0xFF, 0xFF);
         buf.draw(fs, sc, color, |x, y, _w, _h, c| {
             let px = x + ox;
             let py = y + oy;
@@ -497,9 +804,10 @@ impl OverlayState {
             let px = px as usize;
             let py = py as usize;
             if px >= cw || py >= ch { return; }
-            let a = c.a();
+            let a = (c.a() as u32 * alpha as u32 / 255) as u8;
             if a == 0 { return; }
-            put_pixel(canvas, cw, ch, px, py, premul_argb(c.r(), c.g(), c.b(), a));
+            // Glyph alpha is coverage; blend so edges fade into the panel
+            blend_pixel(canvas, cw, ch, px, py, premul_argb(c.r(), c.g(), c.b(), a));
         });
     }
 
@@ -580,17 +888,20 @@ impl OverlayState {
     fn draw_recording(&mut self, qh: &QueueHandle<Self>, width: u32, height: u32) {
         self.poll_cursor();
         let rec_elapsed = self.rec_dot_elapsed();
-
-        let stride = width as i32 * 4;
-        let buf_size = (stride * height as i32) as usize;
-        if self.pool.len() < buf_size {
-            self.pool.resize(buf_size).ok();
-        }
-
-        let (buffer, canvas) = self.pool
-            .create_buffer(width as i32, height as i32, stride, wl_shm::Format::Argb8888)
-            .expect("create buffer");
-        canvas.fill(0);
+        let dot = match self.paused_since {
+            Some(_) => DotState::Paused,
+            None => DotState::Recording(self.link),
+        };
+        let mut timer = self.layout_timer();
+        let timer_w = timer.as_ref().map_or(0.0, |(w, _)| w + TIMER_GAP);
+
+        let (buffer, canvas) = match create_frame(&mut self.pool, width, height) {
+            Ok(frame) => frame,
+            Err(e) => {
+                self.skip_frame(qh, &e);
+                return;
+            }
+        };
 
         let cw = width as usize;
         let ch = height as usize;
@@ -608,14 +919,19 @@ impl OverlayState {
             // Layout at full size to get positions of all glyphs
             let (tw, th, text_buf) = Self::layout_text(
                 &mut self.font_system, &self.text,
-                DISPLAY_FONT_SIZE, DISPLAY_LINE_HEIGHT, max_tw, height as f32,
+                DISPLAY_FONT_SIZE, DISPLAY_LINE_HEIGHT, max_tw,
             );
+            // Long dictations keep their most recent lines in view
+            let (scroll, visible_th) = tail_scroll(&text_buf, th, max_text_height(height));
 
+            // The bottom row holds the timer (if shown) and the cancel button
             pw = (tw + PANEL_PADDING * 2.0).ceil()
-                .max(CANCEL_BTN_WIDTH as f32 + CANCEL_BTN_MARGIN * 2.0) as u32;
-            ph = (PANEL_PADDING + th + CANCEL_BTN_GAP + CANCEL_BTN_HEIGHT as f32 + CANCEL_BTN_MARGIN).ceil() as u32;
-            px = (width as f32 / 2.0 - pw as f32 / 2.0) as i32;
-            py = (height as f32 / 3.0 - ph as f32 / 2.0) as i32;
+                .max(CANCEL_BTN_WIDTH as f32 + CANCEL_BTN_MARGIN * 2.0)
+                .max(PANEL_PADDING + timer_w + CANCEL_BTN_WIDTH as f32 + CANCEL_BTN_MARGIN)
+                as u32;
+            ph = (PANEL_PADDING + visible_th + CANCEL_BTN_GAP + CANCEL_BTN_HEIGHT as f32 + CANCEL_BTN_MARGIN).ceil() as u32;
+            (px, py) = place_panel(self.follow.as_mut(), (self.cursor_x, self.cursor_y),
+                (pw, ph), (width, height));
             let text_ox = px as f32 + PANEL_PADDING;
             let text_oy = py as f32 + PANEL_PADDING;
 
@@ -632,9 +948,15 @@ impl OverlayState {
             let birth_times = &self.char_birth_times;
             let mut glyph_infos: Vec<GlyphDrawInfo> = Vec::new();
 
+            let line_starts = line_char_starts(&self.text);
+
             for run in text_buf.layout_runs() {
-                for glyph in run.glyphs.iter() {
-                    let char_idx = self.text[..glyph.start].chars().count();
+                if run.line_top < scroll {
+                    continue; // scrolled off the top
+                }
+                let line_start = line_starts.get(run.line_i).copied().unwrap_or(0);
+                for cluster in run_clusters(&run) {
+                    let char_idx = line_start + cluster.char_offset;
                     let birth = birth_times.get(char_idx).copied().unwrap_or(now);
                     let elapsed = now.duration_since(birth).as_secs_f32();
 
@@ -650,24 +972,22 @@ impl OverlayState {
                     let scale = 1.0 - (1.0 - t) * (1.0 - t); // ease-out-quad
 
                     glyph_infos.push(GlyphDrawInfo {
-                        x: glyph.x + text_ox,
-                        y: run.line_y + text_oy,
-                        w: glyph.w,
-                        start: glyph.start,
-                        end: glyph.end,
+                        x: cluster.x + text_ox,
+                        y: run.line_y - scroll + text_oy,
+                        w: cluster.w,
+                        cluster: cluster.text.to_string(),
                         scale,
+                        dimmed: self.char_dimmed.get(char_idx).copied().unwrap_or(false),
                     });
                 }
             }
 
             // Draw each glyph
-            let text = self.text.clone();
             for info in &glyph_infos {
                 if info.scale <= 0.001 {
                     continue; // invisible, skip
                 }
 
-                let char_text = &text[info.start..info.end];
                 let font_size = DISPLAY_FONT_SIZE * info.scale;
                 let line_height = DISPLAY_LINE_HEIGHT * info.scale;
 
@@ -679,7 +999,7 @@ impl OverlayState {
                 let metrics = Metrics::new(font_size, line_height);
                 let mut char_buf = TextBuffer::new(&mut self.font_system, metrics);
                 char_buf.set_size(&mut self.font_system, Some(info.w + 20.0), Some(DISPLAY_LINE_HEIGHT + 20.0));
-                char_buf.set_text(&mut self.font_system, char_text,
+                char_buf.set_text(&mut self.font_system, &info.cluster,
                     Attrs::new().family(cosmic_text::Family::SansSerif), Shaping::Advanced);
                 char_buf.shape_until_scroll(&mut self.font_system, false);
 
@@ -690,23 +1010,36 @@ impl OverlayState {
                 let ox = (info.x + x_offset) as i32;
                 let oy = (info.y + y_offset) as i32;
 
-                let alpha = (info.scale * 255.0) as u8;
+                let strength = if info.dimmed { LOW_CONFIDENCE_ALPHA } else { 1.0 };
+                let alpha = (info.scale * strength * 255.0) as u8;
                 Self::render_text(
                     &mut self.font_system, &mut self.swash_cache, &mut char_buf,
                     canvas, cw, ch, ox, oy, alpha,
                 );
             }
 
+            // Timer, left-aligned with the text on the cancel button's row
+            if let Some((_, timer_buf)) = &mut timer {
+                let row_y = py as f32 + ph as f32 - CANCEL_BTN_MARGIN - CANCEL_BTN_HEIGHT as f32;
+                Self::render_text(
+                    &mut self.font_system, &mut self.swash_cache, timer_buf,
+                    canvas, cw, ch, text_ox as i32,
+                    (row_y + (CANCEL_BTN_HEIGHT as f32 - TIMER_FONT_SIZE) / 2.0) as i32,
+                    TIMER_ALPHA,
+                );
+            }
+
             // Recording dot
             draw_rec_dot(canvas, cw, ch,
                 (px + pw as i32) as f32 - RECORDING_DOT_MARGIN,
-                py as f32 + RECORDING_DOT_MARGIN, rec_elapsed);
+                py as f32 + RECORDING_DOT_MARGIN, rec_elapsed, dot);
         } else {
-            // Minimal pill with just the recording dot
-            pw = (RECORDING_DOT_MARGIN * 2.0 + RECORDING_DOT_RADIUS * 2.0 + PANEL_PADDING) as u32;
+            // Minimal pill with the recording dot, and the timer left of it
+            let dot_pill_w = RECORDING_DOT_MARGIN * 2.0 + RECORDING_DOT_RADIUS * 2.0 + PANEL_PADDING;
+            pw = (dot_pill_w + timer_w).ceil() as u32;
             ph = (RECORDING_DOT_MARGIN * 2.0) as u32;
-            px = (width as f32 / 2.0 - pw as f32 / 2.0) as i32;
-            py = (height as f32 / 3.0 - ph as f32 / 2.0) as i32;
+            (px, py) = place_panel(self.follow.as_mut(), (self.cursor_x, self.cursor_y),
+                (pw, ph), (width, height));
 
             Self::draw_tail(canvas, cw, ch, px, py, pw, ph,
                 self.cursor_x, self.cursor_y, fill, 0xFF);
@@ -714,18 +1047,26 @@ impl OverlayState {
             draw_rounded_rect(canvas, cw, ch, px, py, pw, ph,
                 (ph as f32 / 2.0).min(PANEL_CORNER_RADIUS), fill, border, BORDER_WIDTH);
 
-            draw_rec_dot(canvas, cw, ch,
-                width as f32 / 2.0,
-                py as f32 + ph as f32 / 2.0, rec_elapsed);
+            // Without a timer this is the middle of the pill
+            let dot_x = (px + pw as i32) as f32 - dot_pill_w / 2.0;
+            let mid_y = py as f32 + ph as f32 / 2.0;
+            draw_rec_dot(canvas, cw, ch, dot_x, mid_y, rec_elapsed, dot);
+
+            if let Some((tw, timer_buf)) = &mut timer {
+                let timer_x = dot_x - RECORDING_DOT_RADIUS - TIMER_GAP - *tw;
+                Self::render_text(
+                    &mut self.font_system, &mut self.swash_cache, timer_buf,
+                    canvas, cw, ch, timer_x as i32, (mid_y - TIMER_FONT_SIZE / 2.0) as i32,
+                    TIMER_ALPHA,
+                );
+            }
         }
 
         // Cancel button — only shown when there's text
         if self.text.is_empty() {
             self.cancel_btn_rect = None;
             self.pointer_hover = false;
-            if let Ok(region) = Region::new(&self.compositor) {
-                self.layer.wl_surface().set_input_region(Some(region.wl_region()));
-            }
+            self.set_input_rect((!self.click_through).then_some((px, py, pw, ph)));
             self.commit_frame(qh, buffer, width, height);
             return;
         }
@@ -769,21 +1110,32 @@ impl OverlayState {
 
         self.cancel_btn_rect = Some((btn_x, btn_y, CANCEL_BTN_WIDTH, CANCEL_BTN_HEIGHT));
 
-        if let Ok(region) = Region::new(&self.compositor) {
-            region.add(btn_x, btn_y, CANCEL_BTN_WIDTH as i32, CANCEL_BTN_HEIGHT as i32);
-            self.layer.wl_surface().set_input_region(Some(region.wl_region()));
-        }
+        let input_rect = if self.click_through {
+            (btn_x, btn_y, CANCEL_BTN_WIDTH, CANCEL_BTN_HEIGHT)
+        } else {
+            (px, py, pw, ph)
+        };
+        self.set_input_rect(Some(input_rect));
 
         self.commit_frame(qh, buffer, width, height);
     }
 
+    /// Take pointer input only within `rect` (x, y, w, h), or nowhere.
+    fn set_input_rect(&self, rect: Option<(i32, i32, u32, u32)>) {
+        let Ok(region) = Region::new(&self.compositor) else {
+            return;
+        };
+        if let Some((x, y, w, h)) = rect {
+            region.add(x, y, w as i32, h as i32);
+        }
+        self.layer().wl_surface().set_input_region(Some(region.wl_region()));
+    }
+
     fn draw_flyout(&mut self, qh: &QueueHandle<Self>, width: u32, height: u32) {
         // Clear cancel button state and set empty input region during fly-out
         self.cancel_btn_rect = None;
         self.pointer_hover = false;
-        if let Ok(region) = Region::new(&self.compositor) {
-            self.layer.wl_surface().set_input_region(Some(region.wl_region()));
-        }
+        self.set_input_rect(None);
         if self.text.is_empty() {
             self.done = true;
             return;
@@ -799,8 +1151,9 @@ impl OverlayState {
         let eased = ease_in_cubic(t);
 
         // Bezier curve from panel center to cursor with an arc
-        let start_x = width as f32 / 2.0;
-        let start_y = height as f32 / 3.0;
+        let (start_x, start_y) = self.follow.as_ref()
+            .and_then(|f| f.center)
+            .unwrap_or((width as f32 / 2.0, height as f32 / 3.0));
         let end_x = self.cursor_x;
         let end_y = self.cursor_y;
 
@@ -839,9 +1192,9 @@ impl OverlayState {
         };
 
         let max_tw = (width as f32 * 0.8).max(200.0);
-        let (tw, th, mut text_buf) = Self::layout_text(
-            &mut self.font_system, &self.text,
-            font_size, line_height, max_tw, height as f32,
+        let (tw, th, mut text_buf) = Self::layout_dimmed_text(
+            &mut self.font_system, &self.text, &self.char_dimmed,
+            font_size, line_height, max_tw,
         );
 
         let pw = (tw + padding * 2.0).ceil() as u32;
@@ -849,16 +1202,13 @@ impl OverlayState {
         let panel_x = (current_x - pw as f32 / 2.0) as i32;
         let panel_y = (current_y - ph as f32 / 2.0) as i32;
 
-        let stride = width as i32 * 4;
-        let buf_size = (stride * height as i32) as usize;
-        if self.pool.len() < buf_size {
-            self.pool.resize(buf_size).ok();
-        }
-
-        let (buffer, canvas) = self.pool
-            .create_buffer(width as i32, height as i32, stride, wl_shm::Format::Argb8888)
-            .expect("create buffer");
-        canvas.fill(0);
+        let (buffer, canvas) = match create_frame(&mut self.pool, width, height) {
+            Ok(frame) => frame,
+            Err(e) => {
+                self.skip_frame(qh, &e);
+                return;
+            }
+        };
 
         let cw = width as usize;
         let ch = height as usize;
@@ -920,23 +1270,228 @@ impl OverlayState {
         self.recording_start.elapsed().as_secs_f32()
     }
 
+    /// The time recorded so far (pauses excluded) as MM:SS, laid out with
+    /// its width, if `show_timer` is on.
+    fn layout_timer(&mut self) -> Option<(f32, TextBuffer)> {
+        if !self.show_timer {
+            return None;
+        }
+        let paused = self.paused_total + self.paused_since.map_or(Duration::ZERO, |s| s.elapsed());
+        let secs = self.recording_start.elapsed().saturating_sub(paused).as_secs();
+        let label = format!("{:02}:{:02}", secs / 60, secs % 60);
+        let (tw, _, buf) = Self::layout_text(
+            &mut self.font_system, &label, TIMER_FONT_SIZE, TIMER_LINE_HEIGHT, 200.0,
+        );
+        Some((tw, buf))
+    }
+
     fn commit_frame(
-        &self, qh: &QueueHandle<Self>,
-        buffer: smithay_client_toolkit::shm::slot::Buffer,
-        width: u32, height: u32,
+        &mut self, qh: &QueueHandle<Self>, buffer: Buffer, width: u32, height: u32,
     ) {
-        self.layer.wl_surface().damage_buffer(0, 0, width as i32, height as i32);
-        self.layer.wl_surface().frame(qh, self.layer.wl_surface().clone());
-        buffer.attach_to(self.layer.wl_surface()).expect("buffer attach");
-        self.layer.commit();
+        if let Err(e) = buffer.attach_to(self.layer().wl_surface()) {
+            self.skip_frame(qh, &e);
+            return;
+        }
+        self.layer().wl_surface().damage_buffer(0, 0, width as i32, height as i32);
+        self.layer().wl_surface().frame(qh, self.layer().wl_surface().clone());
+        self.layer().commit();
+        self.frame_shown = true;
+        self.frame_failed = false;
+    }
+
+    /// Carry on after a frame couldn't be drawn. Once something is on screen,
+    /// ask for another frame callback to retry; before that no callback would
+    /// ever come, so close the overlay rather than leave it hanging.
+    fn skip_frame(&mut self, qh: &QueueHandle<Self>, error: &dyn std::fmt::Display) {
+        if !self.frame_shown {
+            error!(%error, "overlay couldn't draw its first frame, closing it");
+            self.done = true;
+            return;
+        }
+        if !self.frame_failed {
+            warn!(%error, "failed to draw overlay frame, skipping until it works again");
+            self.frame_failed = true;
+        }
+        self.layer().wl_surface().frame(qh, self.layer().wl_surface().clone());
+        self.layer().commit();
     }
 }
 
-/// Draw a pulsing red recording dot.
-fn draw_rec_dot(canvas: &mut [u8], cw: usize, ch: usize, cx: f32, cy: f32, elapsed: f32) {
+/// A cleared full-surface ARGB buffer from `pool`. Fails if shared memory
+/// can't grow to fit it (e.g. a huge output on a memory-starved system).
+fn create_frame(
+    pool: &mut SlotPool, width: u32, height: u32,
+) -> Result<(Buffer, &mut [u8]), CreateBufferError> {
+    let stride = width as i32 * 4;
+    let (buffer, canvas) =
+        pool.create_buffer(width as i32, height as i32, stride, wl_shm::Format::Argb8888)?;
+    canvas.fill(0);
+    Ok((buffer, canvas))
+}
+
+/// Logical size of an output, falling back to its current mode.
+fn output_size(info: &OutputInfo) -> Option<(i32, i32)> {
+    info.logical_size.or_else(|| info.modes.iter().find(|m| m.current).map(|m| m.dimensions))
+}
+
+/// Draw the recording dot. While recording it pulses green when streaming,
+/// amber while connecting and red when only buffering; it's steady amber
+/// while paused.
+fn draw_rec_dot(
+    canvas: &mut [u8], cw: usize, ch: usize, cx: f32, cy: f32, elapsed: f32, state: DotState,
+) {
+    let (r, g, b) = match state {
+        DotState::Paused => {
+            draw_circle(canvas, cw, ch, cx, cy, RECORDING_DOT_RADIUS, premul_argb(0xFF, 0xB0, 0x30, 0xC0));
+            return;
+        }
+        DotState::Recording(LinkStatus::Connecting) => (0xFF, 0xB0, 0x30),
+        DotState::Recording(LinkStatus::Streaming) => (0x30, 0xD0, 0x60),
+        DotState::Recording(LinkStatus::Buffering) => (0xFF, 0x30, 0x30),
+    };
     let pulse = ((elapsed * 3.0).sin() * 0.5 + 0.5).clamp(0.0, 1.0);
     let a = (100.0 + pulse * 155.0) as u8;
-    draw_circle(canvas, cw, ch, cx, cy, RECORDING_DOT_RADIUS, premul_argb(0xFF, 0x30, 0x30, a));
+    draw_circle(canvas, cw, ch, cx, cy, RECORDING_DOT_RADIUS, premul_argb(r, g, b, a));
+}
+
+/// Top-left corner of a `panel`-sized recording panel on an `output`-sized
+/// surface. Centered in the upper third, or with `follow`, eased toward a spot
+/// just above the cursor (below it near the top edge) and kept on screen.
+fn place_panel(
+    follow: Option<&mut PanelFollow>, cursor: (f32, f32), panel: (u32, u32), output: (u32, u32),
+) -> (i32, i32) {
+    let (pw, ph) = (panel.0 as f32, panel.1 as f32);
+    let (width, height) = (output.0 as f32, output.1 as f32);
+    let Some(follow) = follow else {
+        return ((width / 2.0 - pw / 2.0) as i32, (height / 3.0 - ph / 2.0) as i32);
+    };
+
+    let (cursor_x, cursor_y) = cursor;
+    let above = cursor_y - FOLLOW_CURSOR_GAP - ph / 2.0;
+    let target_y = if above - ph / 2.0 >= FOLLOW_SCREEN_MARGIN {
+        above
+    } else {
+        cursor_y + FOLLOW_CURSOR_GAP + ph / 2.0
+    };
+    let target = (cursor_x, target_y);
+
+    let now = Instant::now();
+    let dt = now.duration_since(follow.last_frame).as_secs_f32();
+    follow.last_frame = now;
+    let (mut cx, mut cy) = match follow.center {
+        Some((x, y)) => {
+            let k = 1.0 - (-dt / FOLLOW_SMOOTHING_SECS).exp();
+            (x + (target.0 - x) * k, y + (target.1 - y) * k)
+        }
+        None => target,
+    };
+
+    // Clamp the center so the whole panel stays on the output; a panel wider
+    // than the output is centered on it instead.
+    let clamp_axis = |c: f32, size: f32, extent: f32| {
+        let lo = size / 2.0 + FOLLOW_SCREEN_MARGIN;
+        let hi = extent - size / 2.0 - FOLLOW_SCREEN_MARGIN;
+        if lo > hi { extent / 2.0 } else { c.clamp(lo, hi) }
+    };
+    cx = clamp_axis(cx, pw, width);
+    cy = clamp_axis(cy, ph, height);
+    follow.center = Some((cx, cy));
+    ((cx - pw / 2.0).round() as i32, (cy - ph / 2.0).round() as i32)
+}
+
+/// Flags, indexed by char like `char_birth_times`, for the characters of
+/// words in `text` with a confidence below `threshold`. `confidence` has one
+/// entry per whitespace-separated word of the whole transcript, while `text`
+/// may be just its tail (the fly-out's "…" and last lines), so words are
+/// matched from the end. Empty (nothing dimmed) if there aren't enough entries.
+fn low_confidence_chars(text: &str, confidence: &[f32], threshold: f32) -> Vec<bool> {
+    let words = text.split_whitespace().count();
+    if threshold <= 0.0 || words == 0 || confidence.len() < words {
+        return Vec::new();
+    }
+    let mut scores = confidence[confidence.len() - words..].iter();
+    let mut in_word = false;
+    let mut dim = false;
+    text.chars()
+        .map(|c| {
+            if c.is_whitespace() {
+                in_word = false;
+                return false;
+            }
+            if !in_word {
+                in_word = true;
+                dim = scores.next().is_some_and(|&score| score < threshold);
+            }
+            dim
+        })
+        .collect()
+}
+
+/// Height available for text in the recording panel, after padding and the
+/// cancel button row.
+fn max_text_height(output_height: u32) -> f32 {
+    output_height as f32 * PANEL_MAX_HEIGHT_FRAC
+        - (PANEL_PADDING + CANCEL_BTN_GAP + CANCEL_BTN_HEIGHT as f32 + CANCEL_BTN_MARGIN)
+}
+
+/// Scroll offset (the top of the first shown line) that keeps the last lines
+/// of `buf` within `max_height`, and the resulting visible text height. Whole
+/// lines only; at least the last line is always shown.
+fn tail_scroll(buf: &TextBuffer, text_height: f32, max_height: f32) -> (f32, f32) {
+    let mut scroll = 0.0;
+    for run in buf.layout_runs() {
+        scroll = run.line_top;
+        if text_height - run.line_top <= max_height {
+            break;
+        }
+    }
+    (scroll, text_height - scroll)
+}
+
+/// Char index where each line of `text` starts. Glyph offsets are bytes into
+/// their own line, while birth times are indexed by char across the whole
+/// text (each `\n` counting as one).
+fn line_char_starts(text: &str) -> Vec<usize> {
+    text.split('\n')
+        .scan(0, |next, line| {
+            let start = *next;
+            *next += line.chars().count() + 1;
+            Some(start)
+        })
+        .collect()
+}
+
+/// One source cluster of a laid-out run.
+struct Cluster<'a> {
+    x: f32,
+    /// Advance of all the cluster's glyphs.
+    w: f32,
+    /// Chars from the start of the line to the cluster.
+    char_offset: usize,
+    text: &'a str,
+}
+
+/// The clusters of `run` in glyph order. Combining marks, emoji sequences
+/// etc. shape to several glyphs over one cluster; it is returned once.
+fn run_clusters<'a>(run: &LayoutRun<'a>) -> Vec<Cluster<'a>> {
+    let mut clusters: Vec<Cluster<'a>> = Vec::new();
+    let mut last_cluster = None;
+    for glyph in run.glyphs.iter() {
+        if last_cluster == Some((glyph.start, glyph.end)) {
+            if let Some(cluster) = clusters.last_mut() {
+                cluster.w += glyph.w;
+            }
+            continue;
+        }
+        last_cluster = Some((glyph.start, glyph.end));
+        let (Some(before), Some(text)) =
+            (run.text.get(..glyph.start), run.text.get(glyph.start..glyph.end))
+        else {
+            continue;
+        };
+        clusters.push(Cluster { x: glyph.x, w: glyph.w, char_offset: before.chars().count(), text });
+    }
+    clusters
 }
 
 /// Info about a glyph's position and animation scale for per-character grow.
@@ -944,9 +1499,25 @@ struct GlyphDrawInfo {
     x: f32,
     y: f32,
     w: f32,
-    start: usize,
-    end: usize,
+    /// Source text of the glyph's cluster (one or more chars).
+    cluster: String,
     scale: f32,
+    /// Part of a low-confidence word.
+    dimmed: bool,
+}
+
+/// cosmic-text falls back to any loaded system face that covers a character
+/// (CJK, Arabic, emoji, ...), so tofu in the overlay means no installed font
+/// has the glyph. Log what was loaded to make that diagnosable.
+fn log_font_coverage(fs: &FontSystem) {
+    let db = fs.db();
+    let has_emoji = db
+        .faces()
+        .any(|face| face.families.iter().any(|(name, _)| name.contains("Emoji")));
+    info!(faces = db.len(), emoji = has_emoji, "loaded system fonts");
+    if !has_emoji {
+        warn!("no emoji font installed, emoji will render as boxes (e.g. pacman -S noto-fonts-emoji)");
+    }
 }
 
 /// Draw a thick line between two points.
@@ -955,10 +1526,11 @@ fn draw_line(
     x0: f32, y0: f32, x1: f32, y1: f32,
     thickness: f32, color: u32,
 ) {
-    let min_x = x0.min(x1).max(0.0) as usize;
-    let max_x = (x0.max(x1) as usize + 1).min(cw);
-    let min_y = y0.min(y1).max(0.0) as usize;
-    let max_y = (y0.max(y1) as usize + 1).min(ch);
+    let pad = thickness / 2.0 + 1.0;
+    let min_x = (x0.min(x1) - pad).max(0.0) as usize;
+    let max_x = ((x0.max(x1) + pad) as usize + 1).min(cw);
+    let min_y = (y0.min(y1) - pad).max(0.0) as usize;
+    let max_y = ((y0.max(y1) + pad) as usize + 1).min(ch);
 
     let dx = x1 - x0;
     let dy = y1 - y0;
@@ -975,9 +1547,7 @@ fn draw_line(
             let proj_x = x0 + t * dx;
             let proj_y = y0 + t * dy;
             let dist = ((fpx - proj_x).powi(2) + (fpy - proj_y).powi(2)).sqrt();
-            if dist <= half {
-                put_pixel(canvas, cw, ch, px, py, color);
-            }
+            plot(canvas, cw, ch, px, py, color, edge_coverage(dist - half));
         }
     }
 }
@@ -1111,3 +1681,50 @@ impl ProvidesRegistryState for OverlayState {
     fn registry(&mut self) -> &mut RegistryState { &mut self.registry_state }
     registry_handlers![OutputState, SeatState];
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blend_half_alpha_red_over_blue() {
+        let mut canvas = premul_argb(0, 0, 0xFF, 0xFF).to_le_bytes();
+        blend_pixel(&mut canvas, 1, 1, 0, 0, premul_argb(0xFF, 0, 0, 0x80));
+        assert_eq!(u32::from_le_bytes(canvas), 0xff80007f);
+    }
+
+    #[test]
+    fn line_char_starts_count_newlines() {
+        assert_eq!(line_char_starts("héllo\n世界\n\nx"), vec![0, 6, 9, 10]);
+    }
+
+    /// Every char of mixed-script text, except line breaks, lands in exactly
+    /// one cluster, at the char index the birth times use.
+    #[test]
+    fn clusters_cover_mixed_scripts() {
+        let text = "héllo 世界 مرحبا 👍🏽\nnaïve e\u{301} 👨‍👩‍👧\n\nend";
+        let mut fs = FontSystem::new();
+        let (_, _, buf) = OverlayState::layout_text(
+            &mut fs, text, DISPLAY_FONT_SIZE, DISPLAY_LINE_HEIGHT, 10_000.0,
+        );
+        let chars: Vec<char> = text.chars().collect();
+        let starts = line_char_starts(text);
+
+        let mut covered = vec![0; chars.len()];
+        for run in buf.layout_runs() {
+            for cluster in run_clusters(&run) {
+                let first = starts[run.line_i] + cluster.char_offset;
+                let len = cluster.text.chars().count();
+                let source: String = chars[first..first + len].iter().collect();
+                assert_eq!(source, cluster.text);
+                for count in &mut covered[first..first + len] {
+                    *count += 1;
+                }
+            }
+        }
+        for (c, count) in chars.iter().zip(&covered) {
+            let expected = if *c == '\n' { 0 } else { 1 };
+            assert_eq!(*count, expected, "char {c:?} covered {count} times");
+        }
+    }
+}
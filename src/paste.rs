@@ -1,6 +1,91 @@
+use crate::config::{AppOverride, Config, PasteMethod};
+use crate::hyprctl::{self, ActiveWindow};
 use anyhow::{Context, Result};
 use std::process::Command;
-use tracing::{info, warn};
+use std::sync::mpsc;
+use tracing::{debug, info, warn};
+
+/// Settings that shape every paste, taken from config.
+#[derive(Debug, Clone, Default)]
+pub struct PasteOptions {
+    /// Also copy to the primary selection, for middle-click paste.
+    pub primary_selection: bool,
+    pub app_overrides: Vec<AppOverride>,
+    /// Log instead of pasting or removing text.
+    pub dry_run: bool,
+    /// In a dry run, still copy the text to the clipboard.
+    pub dry_run_clipboard: bool,
+}
+
+impl PasteOptions {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            primary_selection: config.output.primary_selection,
+            app_overrides: config.app_overrides.clone(),
+            dry_run: config.output.dry_run,
+            dry_run_clipboard: config.output.dry_run_clipboard,
+        }
+    }
+}
+
+/// Keystroke backend for the focused window.
+#[derive(Debug, Clone, Copy)]
+struct Keyboard {
+    /// xdotool for XWayland clients, wtype otherwise.
+    xwayland: bool,
+    delay_ms: Option<u32>,
+}
+
+/// How a paste reaches the focused window.
+enum Delivery {
+    Type(Keyboard),
+    /// Text is already on the clipboard; press this shortcut to paste it.
+    Shortcut(Keyboard, String),
+}
+
+impl Delivery {
+    /// Pick the method for the focused window: a matching `[[app_overrides]]`
+    /// entry first, then the XWayland heuristic.
+    fn for_focused_window(options: &PasteOptions) -> Self {
+        let window = active_window();
+        let over = find_override(&options.app_overrides, &window.class);
+        if let Some(over) = over {
+            debug!(class = %window.class, ?over, "using app override");
+        }
+
+        let method = over.and_then(|o| o.method);
+        let keyboard = Keyboard {
+            xwayland: match method {
+                Some(PasteMethod::Wtype) => false,
+                Some(PasteMethod::Xdotool) => true,
+                _ => window.xwayland,
+            },
+            delay_ms: over.and_then(|o| o.type_delay_ms),
+        };
+        match method {
+            Some(PasteMethod::Clipboard) => {
+                let keys = over.and_then(|o| o.paste_keys.clone());
+                Delivery::Shortcut(keyboard, keys.unwrap_or_else(|| "ctrl+v".to_string()))
+            }
+            _ => Delivery::Type(keyboard),
+        }
+    }
+
+    /// The keyboard for typing and editing keys, whatever the paste method.
+    fn keyboard(&self) -> Keyboard {
+        match self {
+            Delivery::Type(keyboard) | Delivery::Shortcut(keyboard, _) => *keyboard,
+        }
+    }
+}
+
+/// The override for `class`: an exact (case-insensitive) match, else `"*"`.
+fn find_override<'a>(overrides: &'a [AppOverride], class: &str) -> Option<&'a AppOverride> {
+    overrides
+        .iter()
+        .find(|o| o.class.eq_ignore_ascii_case(class))
+        .or_else(|| overrides.iter().find(|o| o.class == "*"))
+}
 
 /// Paste text at the current cursor position.
 ///
@@ -9,65 +94,311 @@ use tracing::{info, warn};
 /// - Native Wayland: `wtype -- text` (virtual keyboard protocol)
 /// - XWayland (Electron, etc.): `xdotool type` (X11 protocol)
 ///
+/// A `[[app_overrides]]` entry for the window's class can force either
+/// method, add a typing delay, or paste from the clipboard with a shortcut.
+///
+/// Line breaks are sent as real Return key presses rather than typed characters.
+///
 /// Also copies text to clipboard via `wl-copy` as a backup.
-pub fn paste_text(text: &str) -> Result<()> {
+///
+/// In a dry run the text is only logged (and copied if `dry_run_clipboard`).
+pub fn paste_text(text: &str, options: &PasteOptions) -> Result<()> {
     if text.is_empty() {
         warn!("empty text, nothing to paste");
         return Ok(());
     }
 
+    if options.dry_run {
+        info!(text = %text, "dry run, not pasting");
+        if options.dry_run_clipboard {
+            copy_to_clipboard(text, options.primary_selection);
+        }
+        return Ok(());
+    }
+
     // Wait for focus to settle after overlay closes
     std::thread::sleep(std::time::Duration::from_millis(150));
 
     // Always copy to clipboard as a backup
+    copy_to_clipboard(text, options.primary_selection);
+
+    match Delivery::for_focused_window(options) {
+        Delivery::Type(keyboard) => {
+            if keyboard.xwayland {
+                info!(len = text.len(), "XWayland window detected, using xdotool");
+            } else {
+                info!(len = text.len(), "native Wayland window, using wtype");
+            }
+            type_lines(keyboard, text)
+        }
+        Delivery::Shortcut(keyboard, keys) => {
+            info!(len = text.len(), keys = %keys, "pasting from clipboard");
+            press_combo(keyboard, &keys)
+        }
+    }
+}
+
+/// Delete the `count` characters before the cursor by sending BackSpace
+/// through the same backend `paste_text` would use for the focused window.
+pub fn backspace(count: usize, options: &PasteOptions) -> Result<()> {
+    if count == 0 {
+        return Ok(());
+    }
+    if options.dry_run {
+        info!(count, "dry run, not sending backspaces");
+        return Ok(());
+    }
+    press_backspace(Delivery::for_focused_window(options).keyboard(), count)?;
+    info!(count, "removed last paste");
+    Ok(())
+}
+
+/// Types streaming partials into the focused window while the user speaks.
+///
+/// Typing runs on its own thread so slow `wtype`/`xdotool` calls never stall
+/// the WebSocket reader. Each update is diffed against what has already been
+/// typed: the changed tail is backspaced and only the new suffix is typed.
+pub struct LiveTyper {
+    tx: mpsc::Sender<LiveUpdate>,
+    join: std::thread::JoinHandle<Result<()>>,
+}
+
+enum LiveUpdate {
+    Partial(String),
+    Final(String),
+}
+
+/// Cloneable sender for partial results, handed to the streaming task.
+#[derive(Clone)]
+pub struct PartialSender(mpsc::Sender<LiveUpdate>);
+
+impl PartialSender {
+    pub fn send(&self, text: &str) {
+        let _ = self.0.send(LiveUpdate::Partial(text.to_string()));
+    }
+}
+
+impl LiveTyper {
+    pub fn spawn(options: PasteOptions) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let join = std::thread::spawn(move || run_live_typer(rx, options));
+        Self { tx, join }
+    }
+
+    pub fn partials(&self) -> PartialSender {
+        PartialSender(self.tx.clone())
+    }
+
+    /// Bring the typed text in line with `text` and wait for typing to finish.
+    /// An empty `text` erases everything typed so far.
+    pub fn finish(self, text: &str) -> Result<()> {
+        let _ = self.tx.send(LiveUpdate::Final(text.to_string()));
+        match self.join.join() {
+            Ok(result) => result,
+            Err(_) => anyhow::bail!("live typing thread panicked"),
+        }
+    }
+}
+
+fn run_live_typer(rx: mpsc::Receiver<LiveUpdate>, options: PasteOptions) -> Result<()> {
+    // The overlay doesn't take focus, so the target window stays the same.
+    // Partials are always typed, even for clipboard-shortcut apps.
+    let keyboard = Delivery::for_focused_window(&options).keyboard();
+    let mut typed = String::new();
+
+    while let Ok(mut update) = rx.recv() {
+        // Skip partials that were superseded while we were busy typing
+        while let LiveUpdate::Partial(_) = update {
+            match rx.try_recv() {
+                Ok(next) => update = next,
+                Err(_) => break,
+            }
+        }
+
+        match update {
+            LiveUpdate::Partial(text) => {
+                // Hold back the trailing word, the part most likely to be revised
+                let stable = match text.rfind(char::is_whitespace) {
+                    Some(i) => &text[..i],
+                    None => "",
+                };
+                if let Err(e) = retype(keyboard, &mut typed, stable) {
+                    warn!(error = %e, "live typing failed, waiting for final text");
+                    break;
+                }
+            }
+            LiveUpdate::Final(text) => {
+                if !text.is_empty() {
+                    copy_to_clipboard(&text, options.primary_selection);
+                }
+                retype(keyboard, &mut typed, &text)?;
+                info!(len = text.len(), "live typing complete");
+                return Ok(());
+            }
+        }
+    }
+
+    // Typing broke off (or the sender went away); the text on screen is unknown
+    while let Ok(update) = rx.recv() {
+        if let LiveUpdate::Final(_) = update {
+            anyhow::bail!("live typing was interrupted; final text not typed");
+        }
+    }
+    Ok(())
+}
+
+/// Replace `typed` on screen with `target`, reusing their common prefix.
+fn retype(keyboard: Keyboard, typed: &mut String, target: &str) -> Result<()> {
+    // Character-level common prefix
+    let common = typed
+        .chars()
+        .zip(target.chars())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let stale = typed.chars().count() - common;
+    if stale == 0 && common == target.chars().count() {
+        return Ok(());
+    }
+
+    if stale > 0 {
+        press_backspace(keyboard, stale)?;
+    }
+    let suffix: String = target.chars().skip(common).collect();
+    type_lines(keyboard, &suffix)?;
+    debug!(erased = stale, typed = %suffix, "live typing update");
+
+    *typed = target.to_string();
+    Ok(())
+}
+
+fn copy_to_clipboard(text: &str, primary_selection: bool) {
     let _ = Command::new("wl-copy")
         .arg("--")
         .arg(text)
         .status();
 
-    if is_xwayland_focused() {
-        info!(len = text.len(), "XWayland window detected, using xdotool");
-        xdotool_paste(text)
-    } else {
-        info!(len = text.len(), "native Wayland window, using wtype");
-        wtype_paste(text)
+    if primary_selection {
+        // Not every compositor implements primary selection; the clipboard
+        // copy above is enough in that case
+        match Command::new("wl-copy").args(["--primary", "--"]).arg(text).status() {
+            Ok(status) if status.success() => {}
+            Ok(status) => warn!(%status, "wl-copy --primary failed, primary selection not set"),
+            Err(e) => warn!(error = %e, "failed to run wl-copy --primary"),
+        }
     }
 }
 
-/// Check if the currently focused window is an XWayland client.
-fn is_xwayland_focused() -> bool {
-    let output = match Command::new("hyprctl")
-        .args(["activewindow", "-j"])
-        .output()
-    {
-        Ok(o) => o,
-        Err(e) => {
-            warn!(error = %e, "failed to run hyprctl, assuming native Wayland");
-            return false;
+/// Type `text`, sending line breaks as real Return key presses.
+fn type_lines(keyboard: Keyboard, text: &str) -> Result<()> {
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            press_key(keyboard, "Return")?;
+        }
+        if line.is_empty() {
+            continue;
         }
+        if keyboard.xwayland {
+            xdotool_paste(line, keyboard.delay_ms)?;
+        } else {
+            wtype_paste(line, keyboard.delay_ms)?;
+        }
+    }
+    Ok(())
+}
+
+/// Press BackSpace `count` times.
+fn press_backspace(keyboard: Keyboard, count: usize) -> Result<()> {
+    let status = if keyboard.xwayland {
+        Command::new("xdotool")
+            .args(["key", "--clearmodifiers", "--repeat", &count.to_string(), "BackSpace"])
+            .status()
+            .context("failed to run xdotool")?
+    } else {
+        let mut cmd = Command::new("wtype");
+        for _ in 0..count {
+            cmd.args(["-k", "BackSpace"]);
+        }
+        cmd.status().context("failed to run wtype")?
     };
 
-    let json: serde_json::Value = match serde_json::from_slice(&output.stdout) {
-        Ok(v) => v,
-        Err(e) => {
-            warn!(error = %e, "failed to parse hyprctl output, assuming native Wayland");
-            return false;
+    if !status.success() {
+        anyhow::bail!("backspace exited with status: {status}");
+    }
+    Ok(())
+}
+
+/// Press and release a single named key (X keysym name, e.g. `Return`).
+fn press_key(keyboard: Keyboard, key: &str) -> Result<()> {
+    let status = if keyboard.xwayland {
+        Command::new("xdotool")
+            .args(["key", "--clearmodifiers", key])
+            .status()
+            .context("failed to run xdotool")?
+    } else {
+        Command::new("wtype")
+            .args(["-k", key])
+            .status()
+            .context("failed to run wtype")?
+    };
+
+    if !status.success() {
+        anyhow::bail!("key press {key} exited with status: {status}");
+    }
+    Ok(())
+}
+
+/// Press a key combination such as `ctrl+shift+v` (modifiers, then a key).
+fn press_combo(keyboard: Keyboard, combo: &str) -> Result<()> {
+    let status = if keyboard.xwayland {
+        Command::new("xdotool")
+            .args(["key", "--clearmodifiers", combo])
+            .status()
+            .context("failed to run xdotool")?
+    } else {
+        let parts: Vec<&str> = combo.split('+').map(str::trim).collect();
+        let (key, modifiers) = parts.split_last().context("empty paste_keys")?;
+        let mut cmd = Command::new("wtype");
+        for modifier in modifiers {
+            cmd.args(["-M", modifier]);
+        }
+        cmd.args(["-k", key]);
+        for modifier in modifiers.iter().rev() {
+            cmd.args(["-m", modifier]);
         }
+        cmd.status().context("failed to run wtype")?
     };
 
-    let xwayland = json.get("xwayland").and_then(|v| v.as_bool()).unwrap_or(false);
-    if xwayland {
-        let class = json.get("class").and_then(|v| v.as_str()).unwrap_or("unknown");
-        info!(class, "focused window is XWayland");
+    if !status.success() {
+        anyhow::bail!("key combo {combo} exited with status: {status}");
+    }
+    Ok(())
+}
+
+/// Query the focused window's class and whether it is an XWayland client.
+/// Falls back to an unknown native Wayland window.
+fn active_window() -> ActiveWindow {
+    match hyprctl::active_window() {
+        Ok(window) => {
+            if window.xwayland {
+                info!(class = %window.class, "focused window is XWayland");
+            }
+            window
+        }
+        Err(e) => {
+            warn!(error = %e, "failed to query hyprctl, assuming native Wayland");
+            ActiveWindow::default()
+        }
     }
-    xwayland
 }
 
 /// Paste via xdotool for XWayland windows (Electron, Chromium, etc.).
-fn xdotool_paste(text: &str) -> Result<()> {
-    let status = Command::new("xdotool")
-        .arg("type")
-        .arg("--clearmodifiers")
+fn xdotool_paste(text: &str, delay_ms: Option<u32>) -> Result<()> {
+    let mut cmd = Command::new("xdotool");
+    cmd.arg("type").arg("--clearmodifiers");
+    if let Some(delay) = delay_ms {
+        cmd.args(["--delay", &delay.to_string()]);
+    }
+    let status = cmd
         .arg("--")
         .arg(text)
         .status()
@@ -82,8 +413,12 @@ fn xdotool_paste(text: &str) -> Result<()> {
 }
 
 /// Paste via direct wtype character simulation for native Wayland windows.
-fn wtype_paste(text: &str) -> Result<()> {
-    let status = Command::new("wtype")
+fn wtype_paste(text: &str, delay_ms: Option<u32>) -> Result<()> {
+    let mut cmd = Command::new("wtype");
+    if let Some(delay) = delay_ms {
+        cmd.args(["-d", &delay.to_string()]);
+    }
+    let status = cmd
         .arg("--")
         .arg(text)
         .status()
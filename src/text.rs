@@ -0,0 +1,272 @@
+use crate::config::{CommandsConfig, Config, Replacement};
+use regex::{NoExpand, Regex};
+use std::borrow::Cow;
+use tracing::{debug, warn};
+
+/// Post-processing applied to the final transcription before it is shown in
+/// the fly-out and pasted.
+pub struct PostProcessor {
+    commands: Option<Commands>,
+    replacements: Vec<Rule>,
+    autoformat: bool,
+    add_period: bool,
+    /// Minimum silence that becomes a paragraph break, if enabled.
+    pause_secs: Option<f32>,
+}
+
+/// Compiled spoken-command patterns.
+struct Commands {
+    new_line: Regex,
+    new_paragraph: Regex,
+    scratch_that: Regex,
+}
+
+impl Commands {
+    fn new(config: &CommandsConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        // Swallow punctuation and spacing the recognizer puts around the phrase
+        let compile = |phrase: &str| {
+            Regex::new(&format!(r"(?i),?\s*\b{}\b[,.;]?\s*", regex::escape(phrase.trim())))
+        };
+        match (
+            compile(&config.new_line),
+            compile(&config.new_paragraph),
+            compile(&config.scratch_that),
+        ) {
+            (Ok(new_line), Ok(new_paragraph), Ok(scratch_that)) => Some(Self {
+                new_line,
+                new_paragraph,
+                scratch_that,
+            }),
+            _ => {
+                warn!("invalid command phrase, spoken commands disabled");
+                None
+            }
+        }
+    }
+
+    fn apply(&self, text: &str) -> String {
+        // "scratch that" drops everything dictated before it
+        let text = match self.scratch_that.find_iter(text).last() {
+            Some(m) => {
+                debug!("spoken command: scratch that");
+                &text[m.end()..]
+            }
+            None => text,
+        };
+        let text = self.new_paragraph.replace_all(text, "\n\n");
+        self.new_line.replace_all(&text, "\n").into_owned()
+    }
+}
+
+struct Rule {
+    pattern: Regex,
+    with: String,
+    /// Regex rules may reference capture groups (`$1`); plain rules are literal.
+    expand: bool,
+}
+
+impl PostProcessor {
+    pub fn new(config: &Config) -> Self {
+        let mut replacements = Vec::new();
+        for (pattern, replacement) in &config.replacements {
+            let (source, with, expand) = match replacement {
+                Replacement::Plain(with) | Replacement::Detailed { with, regex: false } => {
+                    (format!(r"(?i)\b{}\b", regex::escape(pattern)), with, false)
+                }
+                Replacement::Detailed { with, regex: true } => (pattern.clone(), with, true),
+            };
+            match Regex::new(&source) {
+                Ok(re) => replacements.push(Rule {
+                    pattern: re,
+                    with: with.clone(),
+                    expand,
+                }),
+                Err(e) => warn!(pattern = %pattern, error = %e, "invalid replacement pattern, skipping"),
+            }
+        }
+        debug!(count = replacements.len(), "loaded text replacements");
+        Self {
+            commands: Commands::new(&config.commands),
+            replacements,
+            autoformat: config.text.autoformat,
+            add_period: config.text.add_period,
+            pause_secs: config.text.paragraph_on_pause.then_some(config.text.pause_secs),
+        }
+    }
+
+    /// Apply all post-processing steps to `text`, transcribed from `samples`
+    /// (16kHz mono, used to place paragraph breaks at pauses).
+    pub fn apply(&self, text: &str, samples: &[f32]) -> String {
+        // Before commands, which may drop text and shift the positions
+        let text = match self.pause_secs {
+            Some(secs) => {
+                let pauses = crate::audio::find_pauses(samples, secs);
+                debug!(count = pauses.len(), "paragraph pauses");
+                insert_paragraph_breaks(text, &pauses)
+            }
+            None => text.to_string(),
+        };
+        let mut out = match &self.commands {
+            Some(commands) => commands.apply(&text),
+            None => text,
+        };
+        for rule in &self.replacements {
+            let replaced = if rule.expand {
+                rule.pattern.replace_all(&out, rule.with.as_str())
+            } else {
+                rule.pattern.replace_all(&out, NoExpand(&rule.with))
+            };
+            if let Cow::Owned(s) = replaced {
+                out = s;
+            }
+        }
+        if self.autoformat {
+            out = autoformat(&out, self.add_period);
+        }
+        out
+    }
+}
+
+/// Replace the word gap nearest each position (a fraction of the way through
+/// the audio) with a paragraph break. The transcript carries no timestamps,
+/// so speech rate is assumed even: a pause 40% into the recording lands about
+/// 40% into the text.
+fn insert_paragraph_breaks(text: &str, positions: &[f32]) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let gaps: Vec<usize> = (0..chars.len()).filter(|&i| chars[i] == ' ').collect();
+
+    let mut breaks: Vec<usize> = positions
+        .iter()
+        .filter_map(|&p| {
+            let target = p * chars.len() as f32;
+            gaps.iter()
+                .copied()
+                .min_by(|&a, &b| (a as f32 - target).abs().total_cmp(&(b as f32 - target).abs()))
+        })
+        .collect();
+    breaks.sort_unstable();
+    breaks.dedup();
+
+    let mut out = String::with_capacity(text.len() + breaks.len() * 2);
+    for (i, &c) in chars.iter().enumerate() {
+        if breaks.binary_search(&i).is_ok() {
+            out.push_str("\n\n");
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Capitalize the first letter of the text and of every sentence following
+/// `.`, `!`, `?` or a line break. With `add_period`, append a period when the text doesn't
+/// already end in punctuation. Surrounding whitespace is preserved, so the
+/// period goes before a trailing line or paragraph break.
+pub fn autoformat(text: &str, add_period: bool) -> String {
+    let mut out = String::with_capacity(text.len() + 1);
+    let mut capitalize_next = true;
+
+    for c in text.chars() {
+        if capitalize_next && c.is_alphanumeric() {
+            out.extend(c.to_uppercase());
+            capitalize_next = false;
+        } else {
+            out.push(c);
+        }
+        if matches!(c, '.' | '!' | '?' | '\n') {
+            capitalize_next = true;
+        }
+    }
+
+    if add_period {
+        let trimmed_len = out.trim_end().len();
+        let needs_period = out[..trimmed_len]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric());
+        if needs_period {
+            out.insert(trimmed_len, '.');
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn autoformat_keeps_leading_whitespace() {
+        assert_eq!(autoformat("  hello world", true), "  Hello world.");
+    }
+
+    #[test]
+    fn autoformat_keeps_closing_punctuation() {
+        assert_eq!(autoformat("is it done?", true), "Is it done?");
+        assert_eq!(autoformat("it works! great", true), "It works! Great.");
+        assert_eq!(autoformat("wow!", true), "Wow!");
+    }
+
+    #[test]
+    fn autoformat_without_period() {
+        assert_eq!(autoformat("hello. world", false), "Hello. World");
+    }
+
+    #[test]
+    fn autoformat_capitalizes_after_line_break() {
+        assert_eq!(autoformat("first line\nsecond line", true), "First line\nSecond line.");
+    }
+
+    #[test]
+    fn autoformat_puts_period_before_trailing_paragraph_break() {
+        assert_eq!(autoformat("end of thought\n\n", true), "End of thought.\n\n");
+    }
+
+    #[test]
+    fn autoformat_non_ascii_first_letter() {
+        assert_eq!(autoformat("élan vital. über alles", true), "Élan vital. Über alles.");
+    }
+
+    #[test]
+    fn paragraph_breaks_replace_nearest_gap() {
+        assert_eq!(insert_paragraph_breaks("one two three four", &[0.5]), "one two\n\nthree four");
+    }
+
+    #[test]
+    fn paragraph_breaks_without_spaces() {
+        assert_eq!(insert_paragraph_breaks("hello", &[0.5]), "hello");
+        assert_eq!(insert_paragraph_breaks("", &[0.5]), "");
+    }
+
+    #[test]
+    fn paragraph_breaks_merge_pauses_on_same_gap() {
+        assert_eq!(
+            insert_paragraph_breaks("one two three four", &[0.5, 0.52]),
+            "one two\n\nthree four"
+        );
+    }
+
+    #[test]
+    fn paragraph_breaks_at_ends() {
+        assert_eq!(insert_paragraph_breaks("one two three", &[0.0, 1.0]), "one\n\ntwo\n\nthree");
+    }
+
+    #[test]
+    fn replacements_apply_in_file_order() {
+        // Alphabetically "my sql" would run first and never see "SQL"
+        let config: Config = toml::from_str(
+            r#"
+            [replacements]
+            "sequel" = "SQL"
+            "my sql" = "MySQL"
+            "#,
+        )
+        .unwrap();
+        let post = PostProcessor::new(&config);
+        assert_eq!(post.apply("my sequel", &[]), "MySQL");
+    }
+}
@@ -1,58 +1,538 @@
-use crate::config::Config;
+use crate::config::{AudioFormat, Backend, Config};
 use anyhow::{Context, Result};
+use serde::Serialize;
 use std::path::Path;
-use tracing::{info, warn};
+use std::sync::{Arc, Weak};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+use tracing::{debug, info, warn};
+use ureq::Agent;
 use ureq::unversioned::multipart::{Form, Part};
 
+/// Upper bound on the startup health check so a dead server doesn't delay launch.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_millis(1500);
+
 pub struct Transcriber {
-    server_url: String,
+    agent: Agent,
+    /// Configured servers in priority order; never empty.
+    server_urls: Vec<String>,
+    /// Index into `server_urls` of the server in use. Advances when the
+    /// current one can't be reached.
+    current: AtomicUsize,
+    transcribe_path: String,
+    stream_path: String,
+    stream_interval: Duration,
+    stream_min_bytes: usize,
+    backend: Backend,
+    model: String,
+    audio_format: AudioFormat,
+    /// Can be long and personal; only its length is logged.
+    prompt: Option<String>,
+    /// Never logged — may contain tokens.
+    auth_headers: Vec<(String, String)>,
+    health_timeout: Duration,
+    /// Response timeouts for regular and fallback HTTP transcription.
+    timeout: Duration,
+    fallback_timeout: Duration,
+    retries: u32,
+    retry_base: Duration,
 }
 
 impl Transcriber {
-    pub fn new(server_url: Option<String>) -> Self {
-        let server_url = Config::resolve_server_url(server_url);
+    pub fn new(config: &Config, server_url: Option<String>) -> Self {
+        let server_urls = config.resolve_server_urls(server_url);
+        let backend = config.server.backend;
+
+        let connect_timeout = Duration::from_millis(config.server.connect_timeout_ms);
+        let timeout = Duration::from_millis(config.server.timeout_ms);
+        let agent = Agent::new_with_config(
+            Agent::config_builder()
+                .timeout_connect(Some(connect_timeout))
+                .timeout_recv_response(Some(timeout))
+                .timeout_recv_body(Some(timeout))
+                .build(),
+        );
+        info!(
+            connect_ms = connect_timeout.as_millis() as u64,
+            response_ms = timeout.as_millis() as u64,
+            "HTTP timeouts"
+        );
+
+        let prompt = config.server.prompt.clone().filter(|p| !p.trim().is_empty());
+        if let Some(prompt) = &prompt {
+            info!(chars = prompt.chars().count(), "using transcription prompt");
+        }
+
+        let transcribe_path = config
+            .server
+            .transcribe_path
+            .clone()
+            .unwrap_or_else(|| backend.default_transcribe_path().to_string());
 
-        // Non-fatal health check — server may not be up yet
-        let health_url = format!("{}/health", server_url);
-        match ureq::get(&health_url).call() {
-            Ok(_) => info!(server = %server_url, "transcriber ready (nemospeech)"),
-            Err(_) => warn!(
-                server = %server_url,
-                "nemospeech not reachable yet — will connect on first use"
-            ),
+        if server_urls.len() > 1 {
+            info!(servers = ?server_urls, "fallback servers configured");
         }
 
-        Self { server_url }
+        Self {
+            agent,
+            server_urls,
+            current: AtomicUsize::new(0),
+            transcribe_path,
+            stream_path: config.server.stream_path.clone(),
+            stream_interval: Duration::from_millis(config.server.stream_interval_ms.get()),
+            stream_min_bytes: config.server.stream_min_bytes,
+            backend,
+            model: config.server.model.clone(),
+            audio_format: config.server.audio_format,
+            prompt,
+            auth_headers: config.server.auth_headers(),
+            health_timeout: HEALTH_CHECK_TIMEOUT.min(connect_timeout + timeout),
+            timeout,
+            fallback_timeout: config.server.fallback_timeout_ms.map_or(timeout, Duration::from_millis),
+            retries: config.server.retries,
+            retry_base: Duration::from_millis(config.server.retry_base_ms),
+        }
     }
 
-    /// WebSocket URL for streaming transcription.
-    pub fn ws_url(&self) -> String {
-        let base = self.server_url.replace("http://", "ws://").replace("https://", "wss://");
-        format!("{base}/ws/stream")
+    /// Non-fatal startup health check — the server may not be up yet.
+    /// Probes the servers in priority order and switches to the first that
+    /// answers. Returns whether any did.
+    pub fn check_health(&self) -> bool {
+        match self.backend {
+            Backend::Nemospeech => {
+                let healthy = self.probe_health();
+                if healthy {
+                    info!(server = %self.server_url(), "transcriber ready (nemospeech)");
+                } else {
+                    warn!(
+                        server = %self.server_url(),
+                        "nemospeech not reachable yet — will connect on first use"
+                    );
+                }
+                healthy
+            }
+            Backend::Openai => {
+                // OpenAI-style servers have no standard health endpoint
+                info!(
+                    server = %self.server_url(),
+                    model = %self.model,
+                    "transcriber ready (OpenAI-compatible, streaming disabled)"
+                );
+                true
+            }
+        }
     }
 
-    /// Transcribe a WAV file by uploading it to the nemospeech server.
-    pub fn transcribe(&self, wav_path: &Path) -> Result<String> {
-        info!(path = %wav_path.display(), "transcribing via nemospeech");
+    /// Start on the server `previous` was using, if it is still configured,
+    /// so a config reload doesn't send the next request back to a dead primary.
+    pub fn keep_server_of(&self, previous: &Transcriber) {
+        let url = previous.server_url();
+        if let Some(index) = self.server_urls.iter().position(|u| u == url) {
+            self.current.store(index, Ordering::Relaxed);
+        }
+    }
+
+    /// Quietly probe each server's `/health` in priority order, making the
+    /// first healthy one current.
+    fn probe_health(&self) -> bool {
+        let Some(index) = self.server_urls.iter().position(|url| self.probe_server(url)) else {
+            return false;
+        };
+        self.current.store(index, Ordering::Relaxed);
+        true
+    }
+
+    /// Single quiet GET of `{server}/health`.
+    fn probe_server(&self, server_url: &str) -> bool {
+        let health_url = join_url(server_url, "/health");
+        let mut request = self.agent.get(&health_url);
+        for (name, value) in &self.auth_headers {
+            request = request.header(name, value);
+        }
+        request
+            .config()
+            .timeout_global(Some(self.health_timeout))
+            .build()
+            .call()
+            .is_ok()
+    }
+
+    /// Whether the backend supports WebSocket streaming. When it doesn't,
+    /// callers should go straight to `transcribe`.
+    pub fn supports_streaming(&self) -> bool {
+        self.backend == Backend::Nemospeech
+    }
+
+    /// Sample encoding for streamed audio and uploaded WAV files.
+    pub fn audio_format(&self) -> AudioFormat {
+        self.audio_format
+    }
+
+    /// Settings for a streaming session. The server assumes s16le and no
+    /// prompt, so the handshake is only sent when either differs.
+    pub fn stream_options(&self) -> StreamOptions {
+        let handshake = (self.audio_format != AudioFormat::S16le || self.prompt.is_some())
+            .then(|| {
+                let mut start = serde_json::json!({
+                    "type": "start",
+                    "format": self.audio_format.name(),
+                    "sample_rate": 16_000,
+                });
+                if let Some(prompt) = &self.prompt {
+                    start["prompt"] = prompt.as_str().into();
+                }
+                start.to_string()
+            });
+        StreamOptions {
+            audio_format: self.audio_format,
+            handshake,
+            interval: self.stream_interval,
+            min_bytes: self.stream_min_bytes,
+        }
+    }
 
-        let url = format!("{}/transcribe/", self.server_url);
+    /// The server currently in use.
+    fn server_url(&self) -> &str {
+        &self.server_urls[self.current.load(Ordering::Relaxed)]
+    }
 
-        let form = Form::new()
-            .part(
-                "file",
-                Part::file(wav_path)
-                    .context("failed to read WAV file")?
-                    .file_name("audio.wav")
-                    .mime_str("audio/wav")?,
+    /// Switch to the next configured server after the one at `failed`
+    /// couldn't be reached. Does nothing if another caller already moved on,
+    /// or if there is only one server.
+    fn fail_over(&self, failed: usize) {
+        let next = (failed + 1) % self.server_urls.len();
+        if next == failed {
+            return;
+        }
+        if self
+            .current
+            .compare_exchange(failed, next, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+        {
+            warn!(
+                failed = %self.server_urls[failed],
+                next = %self.server_urls[next],
+                "server unreachable, switching to next"
             );
+        }
+    }
 
-        let mut response = ureq::post(&url)
-            .send(form)
-            .context("nemospeech request failed")?;
+    /// WebSocket URL for streaming transcription.
+    fn ws_url(server_url: &str, stream_path: &str) -> String {
+        let base = server_url.replace("http://", "ws://").replace("https://", "wss://");
+        join_url(&base, stream_path)
+    }
 
-        let text = response.body_mut().read_to_string()?.trim().to_string();
+    /// WebSocket upgrade request for streaming transcription to `server_url`,
+    /// carrying any configured auth headers.
+    fn ws_request(&self, server_url: &str) -> Result<Request> {
+        let mut request = Self::ws_url(server_url, &self.stream_path)
+            .into_client_request()
+            .context("invalid WebSocket URL")?;
+        for (name, value) in &self.auth_headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .with_context(|| format!("invalid auth header name {name:?}"))?;
+            let mut value = HeaderValue::from_str(value).context("invalid auth header value")?;
+            value.set_sensitive(true);
+            request.headers_mut().insert(name, value);
+        }
+        Ok(request)
+    }
+
+    /// Open the streaming WebSocket to the current server. If it can't be
+    /// reached, each remaining server is tried in turn.
+    pub async fn connect_stream(&self) -> Result<WsStream> {
+        let mut tried = 1;
+        loop {
+            let current = self.current.load(Ordering::Relaxed);
+            let server_url = &self.server_urls[current];
+            let result = match self.ws_request(server_url) {
+                Ok(request) => tokio_tungstenite::connect_async(request)
+                    .await
+                    .context("failed to connect to nemospeech WebSocket"),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok((ws_stream, _)) => {
+                    info!(server = %server_url, "WebSocket connected for streaming transcription");
+                    return Ok(ws_stream);
+                }
+                Err(e) if tried < self.server_urls.len() => {
+                    warn!(server = %server_url, error = %e, "streaming connection failed");
+                    self.fail_over(current);
+                    tried += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Transcribe a WAV file by uploading it to the configured server.
+    ///
+    /// Connection failures and 5xx responses are retried with exponential
+    /// backoff; 4xx responses fail immediately. A server that can't be
+    /// reached at all is first replaced by the next configured one.
+    pub fn transcribe(&self, wav_path: &Path) -> Result<String> {
+        self.transcribe_within(wav_path, self.timeout)
+    }
+
+    /// `transcribe` for a recording whose streaming transcription failed,
+    /// waiting at most `[server] fallback_timeout_ms` for each response.
+    pub fn transcribe_fallback(&self, wav_path: &Path) -> Result<String> {
+        self.transcribe_within(wav_path, self.fallback_timeout)
+    }
+
+    fn transcribe_within(&self, wav_path: &Path, timeout: Duration) -> Result<String> {
+        info!(path = %wav_path.display(), backend = ?self.backend, "transcribing");
+
+        let body = self.post_with_retries(wav_path, false, timeout)?;
+        let text = match self.backend {
+            Backend::Nemospeech => body.trim().to_string(),
+            Backend::Openai => parse_openai_response(&body)?,
+        };
 
         info!(text = %text, "transcription complete");
         Ok(text)
     }
+
+    /// Transcribe a WAV file into timed segments, for subtitles and other
+    /// downstream use. Asks nemospeech for `timestamps=true`, or OpenAI-style
+    /// servers for `verbose_json`; both answer with a `segments` array.
+    pub fn transcribe_detailed(&self, wav_path: &Path) -> Result<Vec<Segment>> {
+        info!(path = %wav_path.display(), backend = ?self.backend, "transcribing with timestamps");
+
+        let body = self.post_with_retries(wav_path, true, self.timeout)?;
+        let segments = parse_segments(&body)?;
+
+        info!(count = segments.len(), "transcription complete");
+        Ok(segments)
+    }
+
+    /// Upload the WAV, retrying connection failures and 5xx responses, and
+    /// moving on to the next server when one is unreachable.
+    fn post_with_retries(&self, wav_path: &Path, detailed: bool, timeout: Duration) -> Result<String> {
+        let mut attempt = 0;
+        let mut tried = 1;
+
+        loop {
+            let current = self.current.load(Ordering::Relaxed);
+            match self.post_wav(&self.server_urls[current], wav_path, detailed, timeout) {
+                Ok(body) => return Ok(body),
+                Err(e) if tried < self.server_urls.len() && is_unreachable(&e) => {
+                    warn!(error = %e, "transcription server unreachable");
+                    self.fail_over(current);
+                    tried += 1;
+                }
+                Err(e) if attempt < self.retries && is_retryable(&e) => {
+                    let delay = self.retry_base * 2u32.saturating_pow(attempt);
+                    attempt += 1;
+                    warn!(
+                        error = %e,
+                        attempt,
+                        max_retries = self.retries,
+                        delay_ms = delay.as_millis() as u64,
+                        "transcription request failed, retrying"
+                    );
+                    std::thread::sleep(delay);
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| {
+                        format!("transcription request failed after {} attempt(s)", attempt + 1)
+                    });
+                }
+            }
+        }
+    }
+
+    /// Upload the WAV and return the raw response body. `detailed` requests
+    /// the JSON response with segment timestamps; `timeout` bounds the wait
+    /// for the response headers and then for the body.
+    fn post_wav(
+        &self,
+        server_url: &str,
+        wav_path: &Path,
+        detailed: bool,
+        timeout: Duration,
+    ) -> Result<String, ureq::Error> {
+        let file = Part::file(wav_path)?
+            .file_name("audio.wav")
+            .mime_str("audio/wav")?;
+
+        let mut form = match self.backend {
+            Backend::Nemospeech => Form::new().part("file", file),
+            Backend::Openai => Form::new().part("file", file).text("model", &self.model),
+        };
+        if let Some(prompt) = &self.prompt {
+            form = form.text("prompt", prompt);
+        }
+        if detailed {
+            form = match self.backend {
+                Backend::Nemospeech => form.text("timestamps", "true"),
+                Backend::Openai => form
+                    .text("response_format", "verbose_json")
+                    .text("timestamp_granularities[]", "segment"),
+            };
+        }
+
+        let url = join_url(server_url, &self.transcribe_path);
+        let mut request = self.agent.post(&url);
+        for (name, value) in &self.auth_headers {
+            request = request.header(name, value);
+        }
+        let mut response = request
+            .config()
+            .timeout_recv_response(Some(timeout))
+            .timeout_recv_body(Some(timeout))
+            .build()
+            .send(form)?;
+
+        response.body_mut().read_to_string()
+    }
+}
+
+/// A stretch of transcript with its position in the audio, in seconds.
+#[derive(Debug, Clone, Serialize)]
+pub struct Segment {
+    pub text: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+pub type WsStream =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// What a streaming session needs besides the connection itself.
+#[derive(Debug, Clone)]
+pub struct StreamOptions {
+    pub audio_format: AudioFormat,
+    /// JSON text message sent before any audio, if there's anything to declare.
+    pub handshake: Option<String>,
+    /// How often new audio is sent.
+    pub interval: Duration,
+    /// Audio is held back until at least this much is pending (except the
+    /// last chunk).
+    pub min_bytes: usize,
+}
+
+/// Run the startup health check, then keep re-probing every `interval` in the
+/// background until the server answers, so readiness is logged before the
+/// first dictation. A zero interval checks once. The probe only holds a weak
+/// reference and stops once the transcriber is dropped (e.g. replaced on a
+/// config reload).
+pub fn spawn_health_probe(transcriber: &Arc<Transcriber>, interval: Duration) {
+    let transcriber = Arc::downgrade(transcriber);
+    tokio::spawn(async move {
+        let Some(t) = transcriber.upgrade() else {
+            return;
+        };
+        let healthy = tokio::task::spawn_blocking(move || t.check_health())
+            .await
+            .unwrap_or(false);
+        if healthy || interval.is_zero() {
+            return;
+        }
+
+        info!(interval_ms = interval.as_millis() as u64, "retrying health check in background");
+        loop {
+            tokio::time::sleep(interval).await;
+            let Some(t) = Weak::upgrade(&transcriber) else {
+                debug!("transcriber replaced, stopping health check");
+                return;
+            };
+            let probe = t.clone();
+            match tokio::task::spawn_blocking(move || probe.probe_health()).await {
+                Ok(true) => {
+                    info!(server = %t.server_url(), "transcription server is now reachable");
+                    return;
+                }
+                Ok(false) => debug!(servers = ?t.server_urls, "health check failed"),
+                Err(e) => {
+                    warn!(error = %e, "health check task failed");
+                    return;
+                }
+            }
+        }
+    });
+}
+
+/// Join a base URL and a path with exactly one slash between them. A trailing
+/// slash on `path` is preserved, since some endpoints require it.
+fn join_url(base: &str, path: &str) -> String {
+    format!("{}/{}", base.trim_end_matches('/'), path.trim_start_matches('/'))
+}
+
+/// Extract the transcript from an OpenAI-style `{"text": "..."}` response.
+fn parse_openai_response(body: &str) -> Result<String> {
+    let json: serde_json::Value =
+        serde_json::from_str(body).context("invalid JSON from transcription server")?;
+    let text = json["text"]
+        .as_str()
+        .context("transcription response missing \"text\" field")?;
+    Ok(text.trim().to_string())
+}
+
+/// Extract segments from a `{"segments": [{"start", "end", "text"}]}` response.
+fn parse_segments(body: &str) -> Result<Vec<Segment>> {
+    let json: serde_json::Value =
+        serde_json::from_str(body).context("invalid JSON from transcription server")?;
+    let segments = json["segments"]
+        .as_array()
+        .context("transcription response missing \"segments\" field")?;
+    segments
+        .iter()
+        .map(|seg| {
+            let text = seg["text"].as_str().context("segment missing \"text\"")?;
+            Ok(Segment {
+                text: text.trim().to_string(),
+                start: seg["start"].as_f64().context("segment missing \"start\"")?,
+                end: seg["end"].as_f64().context("segment missing \"end\"")?,
+            })
+        })
+        .collect()
+}
+
+/// Whether a request failed because the server couldn't be reached at all,
+/// as opposed to failing while handling it.
+fn is_unreachable(err: &ureq::Error) -> bool {
+    match err {
+        ureq::Error::HostNotFound
+        | ureq::Error::ConnectionFailed
+        | ureq::Error::Timeout(ureq::Timeout::Resolve | ureq::Timeout::Connect) => true,
+        ureq::Error::Io(e) => matches!(
+            e.kind(),
+            std::io::ErrorKind::ConnectionRefused
+                | std::io::ErrorKind::HostUnreachable
+                | std::io::ErrorKind::NetworkUnreachable
+        ),
+        _ => false,
+    }
+}
+
+/// Whether a failed request is worth retrying: transport errors and server
+/// errors are, client errors (bad request, auth) are not.
+fn is_retryable(err: &ureq::Error) -> bool {
+    match err {
+        ureq::Error::StatusCode(code) => *code >= 500,
+        ureq::Error::Io(_)
+        | ureq::Error::Timeout(_)
+        | ureq::Error::HostNotFound
+        | ureq::Error::ConnectionFailed
+        | ureq::Error::BodyStalled => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn join_url_single_slash() {
+        assert_eq!(join_url("http://h/", "/transcribe/"), "http://h/transcribe/");
+        assert_eq!(join_url("http://h", "ws/stream"), "http://h/ws/stream");
+        assert_eq!(join_url("http://h/api/v1/", "/ws/stream"), "http://h/api/v1/ws/stream");
+    }
 }